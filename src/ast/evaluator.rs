@@ -1,73 +1,200 @@
 use std::collections::HashMap;
-use log::debug;
-use crate::ast::{ASTBinaryExpression, ASTBinaryOperatorKind, ASTExpression, ASTNumberExpression, ASTStatement, ASTVariableExpression, ASTVisitor, GrammarVartype, ASTAssignment, LeftValue};
-use crate::ast::expression::ASTFuncionCall;
+use crate::ast::{ASTBinaryExpression, ASTExpression, ASTNumberExpression, ASTStatement, ASTVariableExpression, ASTVisitor, ASTAssignment, GrammarVartype, LeftValue};
+use crate::ast::expression::{ASTBinaryOperator, ASTCastExpression, ASTConditionalExpression, ASTExpressionKind, ASTFuncionCall, ASTIncrementExpression, ASTMemberExpression, ASTStringExpression, ASTUnaryExpression, ASTUnaryOperatorKind};
 use crate::ast::lexer::TextSpan;
+use crate::ast::memory::Memory;
+use crate::ast::scope::ScopeStack;
 use crate::ast::progranunit::{ASTFunction, ASTProgramunit, ASTProgramunitKind};
-use crate::ast::statement::{ASTDeclaration, ASTDeclarationList};
+use crate::ast::value::Value;
+use crate::ast::statement::{ASTCaseStatement, ASTDeclaration, ASTDeclarationList, ASTDoWhileStatement, ASTForStatement, ASTIfStatement, ASTJumpTarget, ASTLabeledStatement, ASTStatementKind, ASTSwitchStatement, ASTWhileStatement};
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+
+/// How the last statement left the statements enclosing it.
+#[derive(Debug,Clone,PartialEq)]
+enum ControlFlow {
+    Normal,
+    Break,
+    Continue,
+    /// Leaving the running function with its result.
+    Return(Option<Value>),
+    /// Jumping to a label of a `goto` or a `switch`: statements are skipped until the one defining it.
+    Jump(ASTJumpTarget),
+    /// Stopping the program after a run-time error.
+    Fault,
+}
+
+/// Where a variable lives in [`Memory`] and how to read it.
+#[derive(Debug,Clone)]
+struct Variable {
+    address: usize,
+    ty: GrammarVartype,
+}
+
+/// The parameters and locals of one function invocation.
+struct Frame {
+    variables: ScopeStack<Variable>,
+    /// Memory marks taken when each block scope was entered, released when it is left.
+    marks: Vec<usize>,
+    /// The memory mark taken when the call started.
+    base: usize,
+    /// What `return` converts its value to.
+    returntype: GrammarVartype,
+}
 
 pub struct ASTEvaluator {
-    pub last_value: Option<i64>,
-    variable_map: HashMap<String, Option<i64>>,
-    function_map: HashMap<String, ASTFunction>,
-    argument_transition: Vec<i64>,
+    pub last_value: Option<Value>,
+    /// What `main` returned, once the program has run.
+    pub result: Option<i64>,
+    /// The run-time error that stopped the program, if any.
+    pub fault: Option<Diagnostic>,
+    /// The operator or call evaluated last, where a run-time error is reported.
+    location: Option<TextSpan>,
+    control: ControlFlow,
+    globals: ScopeStack<Variable>,
+    frames: Vec<Frame>,
+    memory: Memory,
+    /// The defined functions by the address of the one-byte object standing for each.
+    functions: HashMap<usize, ASTFunction>,
+    /// The static arrays of the string literals evaluated so far; equal literals share one.
+    strings: HashMap<Vec<u8>, usize>,
+}
+
+impl Default for ASTEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'a> ASTEvaluator {
+impl ASTEvaluator {
+    /// Runs `main` once every program unit has been visited, so that it sees all globals and functions.
+    pub fn run_main(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        let Some(main) = self.globals.lookup("main").and_then(|main| self.functions.get(&main.address)).cloned() else {
+            return;
+        };
+        // Falling off the end of `main` returns 0.
+        self.result = Some(self.visit_intepret_function(&main, Vec::new()).map_or(0, Value::as_integer));
+    }
     pub fn new() -> Self {
         Self {
             last_value: None,
-            variable_map: HashMap::new(),
-            function_map: HashMap::new(),
-            argument_transition: Vec::new(),
+            result: None,
+            fault: None,
+            location: None,
+            control: ControlFlow::Normal,
+            globals: ScopeStack::new(),
+            frames: Vec::new(),
+            memory: Memory::new(),
+            functions: HashMap::new(),
+            strings: HashMap::new(),
         }
     }
 }
 
-impl<'a> ASTVisitor for ASTEvaluator {
+impl ASTVisitor for ASTEvaluator {
     fn visit_statement(&mut self, statement: &ASTStatement) {
-        self.last_value = Some(0);
-        self.do_visit_statement(statement);
+        self.last_value = Some(Value::Integer(0));
+        if self.fault.is_none() {
+            self.do_visit_statement(statement);
+        }
+        // A run-time error overrides the control flow the statement started, such as its `return`.
+        if self.fault.is_some() {
+            self.control = ControlFlow::Fault;
+        }
     }
 
-
-
     fn visit_expression(&mut self, expr: &ASTExpression) {
         self.do_visit_expression(expr);
     }
 
     fn visit_assignment(&mut self, assignment: &ASTAssignment) {
-        self.do_visit_expression(&assignment.expr);
-        match &assignment.name {
-            LeftValue::Variable(name)=> {
-                self.variable_map.insert(name.clone(),self.last_value);
-            }
+        let mut value = self.evaluate_value(&assignment.expr);
+        let mut ty = assignment.expr.ty.clone();
+        let object = self.left_value_object(&assignment.name);
+        if let Some(operator) = &assignment.operator {
+            let old = self.load(object.address,&object.ty);
+            value = self.apply_binary_operator(operator,old,&object.ty,value,&ty);
+            ty = operator.result_type(&object.ty,&ty);
         }
+        self.store(object.address,&object.ty,value.convert(&ty,&object.ty));
+        self.last_value = Some(self.load(object.address,&object.ty));
+    }
+
+    fn visit_increment(&mut self, increment: &ASTIncrementExpression) {
+        let object = self.left_value_object(&increment.name);
+        let step = object.ty.pointee().map_or(1, |pointee| pointee.size() as i64);
+        let old = self.load(object.address,&object.ty);
+        let new = match old {
+            Value::Integer(value) => Value::Integer(value.wrapping_add(increment.kind.delta() * step)),
+            Value::Floating(value) => Value::Floating(value + increment.kind.delta() as f64).convert(&object.ty,&object.ty),
+        };
+        self.store(object.address,&object.ty,new);
+        let new = self.load(object.address,&object.ty);
+        self.last_value = Some(if increment.postfix { old } else { new });
     }
 
     fn visit_number(&mut self, number: &ASTNumberExpression) {
         self.last_value = Some(number.value);
     }
 
+    fn visit_string(&mut self, string: &ASTStringExpression) {
+        let address = self.string_address(string);
+        self.last_value = Some(Value::Integer(address as i64));
+    }
+
     fn visit_binary_expression(&mut self, binary: &ASTBinaryExpression) {
         self.visit_expression(&binary.left);
         let left = self.last_value.unwrap();
+        if let Some(value) = binary.operator.short_circuit(left.is_true() as i64) {
+            self.last_value = Some(Value::Integer(value));
+            return;
+        }
         self.visit_expression(&binary.right);
         let right = self.last_value.unwrap();
-        self.last_value = Some(match binary.operator.kind {
-            ASTBinaryOperatorKind::Add => left + right,
-            ASTBinaryOperatorKind::Sub => left - right,
-            ASTBinaryOperatorKind::Mul => left * right,
-            ASTBinaryOperatorKind::Div => left / right,
-            ASTBinaryOperatorKind::Equal => right,
-        })
+        self.last_value = Some(self.apply_binary_operator(&binary.operator,left,&binary.left.ty,right,&binary.right.ty));
+    }
+    fn visit_error(&mut self, _span: &TextSpan) {
+        self.last_value = Some(Value::Integer(0));
+    }
+
+    fn visit_unary_expression(&mut self, unary: &ASTUnaryExpression) {
+        let value = match unary.operator.kind {
+            ASTUnaryOperatorKind::AddressOf => Value::Integer(self.expression_address(&unary.operand) as i64),
+            ASTUnaryOperatorKind::Dereference => {
+                let object = self.dereference(unary);
+                self.load(object.address,&object.ty)
+            }
+            _ => {
+                let operand = self.evaluate_value(&unary.operand);
+                unary.operator.evaluate_value(operand,&unary.operand.ty).expect("Not an arithmetic operator")
+            }
+        };
+        self.last_value = Some(value);
+    }
+
+    fn visit_cast_expression(&mut self, cast: &ASTCastExpression, ty: &GrammarVartype) {
+        let value = self.evaluate_value(&cast.operand);
+        self.last_value = Some(value.convert(&cast.operand.ty,ty));
     }
-    fn visit_error(&mut self, span: &TextSpan) {
-        self.last_value = Some(0);
+
+    fn visit_conditional_expression(&mut self, conditional: &ASTConditionalExpression) {
+        if self.evaluate_condition(&conditional.condition) {
+            self.visit_expression(&conditional.then_expr);
+        } else {
+            self.visit_expression(&conditional.else_expr);
+        }
+    }
+
+    fn visit_member_expression(&mut self, member: &ASTMemberExpression) {
+        let address = self.member_address(member);
+        self.last_value = Some(self.load(address,&member.ty));
     }
 
     fn visit_variable(&mut self, variable: &ASTVariableExpression) {
-        self.last_value= Some(self.variable_map.get(&variable.name).unwrap().expect("Variable not found"));
+        let variable = self.lookup_variable(&variable.name);
+        self.last_value= Some(self.load(variable.address,&variable.ty));
     }
 
     fn visit_declaration_list(&mut self, declaration_list: &ASTDeclarationList) {
@@ -76,12 +203,21 @@ impl<'a> ASTVisitor for ASTEvaluator {
 
     fn visit_declaration(&mut self, declaration: &ASTDeclaration) {
         match declaration {
-            ASTDeclaration::VariableDeclareDirect(name) => {
-                self.variable_map.insert(name.clone(), None);
+            ASTDeclaration::VariableDeclareDirect(name,vartype) => {
+                self.declare_variable(name,vartype);
             }
-            ASTDeclaration::VariableDeclareWithInit(name,expr) => {
-                self.do_visit_expression(expr);
-                self.variable_map.insert(name.clone(),self.last_value);
+            ASTDeclaration::VariableDeclareWithInit(name,vartype,expr) => {
+                let value = self.evaluate_value(expr).convert(&expr.ty,vartype);
+                let address = self.declare_variable(name,vartype);
+                if vartype.is_array() {
+                    // A string literal initializing a char array, which may be longer.
+                    let bytes = self.memory.read(value.as_integer() as usize,expr.ty.size().min(vartype.size()));
+                    if bytes.and_then(|bytes| self.memory.write(address,&bytes)).is_none() {
+                        self.fail_access(address);
+                    }
+                } else {
+                    self.store(address,vartype,value);
+                }
             }
         }
 
@@ -99,41 +235,382 @@ impl<'a> ASTVisitor for ASTEvaluator {
     }
 
     fn visit_function(&mut self, function:&ASTFunction) {
-        self.function_map.insert(function.name.clone(),function.clone());
-        if(function.name=="main"){
-            self.visit_intepret_function(function);
-        }
+        // Each function gets an address of its own, so that pointers to it are plain addresses.
+        let address = self.memory.allocate(1,1);
+        let ty = GrammarVartype::Function(Box::new(function.function_type.clone()));
+        self.globals.declare(function.name.clone(),Variable { address, ty });
+        self.functions.insert(address,function.clone());
     }
 
     fn visit_return(&mut self, expr: &ASTExpression) {
-        self.visit_expression(expr);
+        let value = self.evaluate_value(expr);
+        let returntype = &self.frames.last().expect("Return outside of a function").returntype;
+        self.control = ControlFlow::Return(Some(value.convert(&expr.ty,returntype)));
     }
 
     fn visit_empty_return(&mut self) {
-        self.last_value = None;
+        self.control = ControlFlow::Return(None);
+    }
+
+    fn visit_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        if let ControlFlow::Jump(target) = &self.control {
+            if if_statement.then_branch.contains_target(target) {
+                self.visit_statement(&if_statement.then_branch);
+            } else if let Some(else_branch) = &if_statement.else_branch {
+                self.visit_statement(else_branch);
+            }
+            return;
+        }
+        if self.evaluate_condition(&if_statement.condition) {
+            self.visit_statement(&if_statement.then_branch);
+        } else if let Some(else_branch) = &if_statement.else_branch {
+            self.visit_statement(else_branch);
+        }
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        loop {
+            if !self.is_seeking_label() && !self.evaluate_condition(&while_statement.condition) {
+                break;
+            }
+            self.visit_statement(&while_statement.body);
+            if self.end_loop_iteration() {
+                break;
+            }
+        }
+    }
+
+    fn visit_do_while_statement(&mut self, do_while_statement: &ASTDoWhileStatement) {
+        loop {
+            self.visit_statement(&do_while_statement.body);
+            if self.end_loop_iteration() || !self.evaluate_condition(&do_while_statement.condition) {
+                break;
+            }
+        }
+    }
+
+    fn visit_for_statement(&mut self, for_statement: &ASTForStatement) {
+        self.push_scope();
+        self.visit_for_clauses(for_statement);
+        self.pop_scope();
+    }
+
+    fn visit_block(&mut self, statements: &[ASTStatement]) {
+        self.push_scope();
+        self.visit_statements(statements);
+        self.pop_scope();
+    }
+
+    fn visit_break(&mut self) {
+        self.control = ControlFlow::Break;
+    }
+
+    fn visit_continue(&mut self) {
+        self.control = ControlFlow::Continue;
+    }
+
+    fn visit_goto(&mut self, label: &str) {
+        self.control = ControlFlow::Jump(ASTJumpTarget::Label(label.to_string()));
+    }
+
+    fn visit_labeled_statement(&mut self, labeled: &ASTLabeledStatement) {
+        self.reach_target(ASTJumpTarget::Label(labeled.label.clone()));
+        self.visit_statement(&labeled.statement);
+    }
+
+    fn visit_switch_statement(&mut self, switch: &ASTSwitchStatement) {
+        if !self.is_seeking_label() {
+            let value = self.evaluate_value(&switch.condition).as_integer();
+            let target = [ASTJumpTarget::Case(value), ASTJumpTarget::Default].into_iter()
+                .find(|target| switch.body.contains_target(target));
+            let Some(target) = target else {
+                return;
+            };
+            self.control = ControlFlow::Jump(target);
+        }
+        self.visit_statement(&switch.body);
+        if self.control == ControlFlow::Break {
+            self.control = ControlFlow::Normal;
+        }
+    }
+
+    fn visit_case_statement(&mut self, case: &ASTCaseStatement) {
+        self.reach_target(ASTJumpTarget::Case(case.value));
+        self.visit_statement(&case.statement);
+    }
+
+    fn visit_default_statement(&mut self, statement: &ASTStatement) {
+        self.reach_target(ASTJumpTarget::Default);
+        self.visit_statement(statement);
     }
 
     fn visit_function_call(&mut self, function_call: &ASTFuncionCall) {
-        function_call.param_list.iter().for_each(|expr|{
+        let arguments: Vec<Value> = function_call.param_list.iter().map(|expr|{
             self.last_value = None;
             self.do_visit_expression(expr);
-            self.argument_transition.push(self.last_value.unwrap());
-        });
-        let func : ASTFunction;
-        {
-            func = self.function_map.get(&function_call.name).unwrap().clone();
+            self.last_value.expect("Argument has no value")
+        }).collect();
+        let address = self.evaluate_address(&function_call.callee);
+        self.location = Some(function_call.paren.span.clone());
+        let func = self.functions.get(&address).cloned();
+        if func.is_none() {
+            self.fail(format!("Call through a pointer to no function: address {}",address));
+        }
+        // After a run-time error nothing runs any more, and the values computed are never used.
+        let Some(func) = func.filter(|_| self.fault.is_none()) else {
+            self.last_value = Some(Value::Integer(0));
+            return;
+        };
+        let arguments = arguments.into_iter().zip(&function_call.param_list).zip(&func.params)
+            .map(|((arg,expr),param)| arg.convert(&expr.ty,&param.param_type))
+            .collect();
+        self.last_value = self.visit_intepret_function(&func,arguments);
+        if self.fault.is_some() {
+            self.last_value = Some(Value::Integer(0));
         }
-        self.visit_intepret_function(&func);
     }
 }
 
 impl ASTEvaluator{
-    fn visit_intepret_function(&mut self, function: &ASTFunction) {
-        for param in function.params.iter().rev() {
-            if let Some(arg) = self.argument_transition.pop() {
-                self.variable_map.insert(param.name.clone(), Some(arg));
+    fn visit_for_clauses(&mut self, for_statement: &ASTForStatement) {
+        if let Some(init) = &for_statement.init {
+            if !self.is_seeking_label() {
+                self.visit_statement(init);
+            }
+        }
+        loop {
+            if let Some(condition) = &for_statement.condition {
+                if !self.is_seeking_label() && !self.evaluate_condition(condition) {
+                    break;
+                }
+            }
+            self.visit_statement(&for_statement.body);
+            if self.end_loop_iteration() {
+                break;
+            }
+            if let Some(step) = &for_statement.step {
+                self.visit_expression(step);
+            }
+        }
+    }
+    fn evaluate_value(&mut self, expr: &ASTExpression) -> Value {
+        self.visit_expression(expr);
+        self.last_value.expect("Expression has no value")
+    }
+    /// Evaluates a pointer, or anything else used through its address.
+    fn evaluate_address(&mut self, expr: &ASTExpression) -> usize {
+        self.evaluate_value(expr).as_integer() as usize
+    }
+    fn evaluate_condition(&mut self, condition: &ASTExpression) -> bool {
+        self.evaluate_value(condition).is_true()
+    }
+    fn is_seeking_label(&self) -> bool {
+        matches!(self.control, ControlFlow::Jump(_))
+    }
+    /// Stops skipping statements when the pending jump was to `target`.
+    fn reach_target(&mut self, target: ASTJumpTarget) {
+        if self.control == ControlFlow::Jump(target) {
+            self.control = ControlFlow::Normal;
+        }
+    }
+    /// Handles how the body of a loop ended, returning whether the loop must stop.
+    fn end_loop_iteration(&mut self) -> bool {
+        match self.control {
+            ControlFlow::Normal => false,
+            ControlFlow::Continue => {
+                self.control = ControlFlow::Normal;
+                false
+            }
+            ControlFlow::Break => {
+                self.control = ControlFlow::Normal;
+                true
+            }
+            ControlFlow::Return(_) | ControlFlow::Jump(_) | ControlFlow::Fault => true,
+        }
+    }
+    /// Runs the statements in order, stopping at `break`, `continue` or `return`. While a jump
+    /// is pending, resumes from the statement defining its target or leaves when there is none.
+    fn visit_statements(&mut self, statements: &[ASTStatement]) {
+        let mut index = 0;
+        loop {
+            if let ControlFlow::Jump(target) = &self.control {
+                match statements.iter().position(|statement| statement.contains_target(target)) {
+                    Some(target) => {
+                        self.declare_jumped_over(statements.get(index..target).unwrap_or_default());
+                        index = target;
+                    }
+                    None => return,
+                }
+            }
+            let Some(statement) = statements.get(index) else {
+                return;
+            };
+            self.visit_statement(statement);
+            index += 1;
+            if matches!(self.control, ControlFlow::Break | ControlFlow::Continue | ControlFlow::Return(_) | ControlFlow::Fault) {
+                return;
+            }
+        }
+    }
+    /// Allocates the variables of the declarations a jump passes over, which are in scope at its
+    /// target but keep indeterminate values, as their initializers do not run.
+    fn declare_jumped_over(&mut self, statements: &[ASTStatement]) {
+        for statement in statements {
+            let ASTStatementKind::Declaration(declaration_list) = &statement.kind else {
+                continue;
+            };
+            for declaration in &declaration_list.declare_list {
+                let (ASTDeclaration::VariableDeclareDirect(name,vartype) | ASTDeclaration::VariableDeclareWithInit(name,vartype,_)) = declaration;
+                self.declare_variable(name,vartype);
+            }
+        }
+    }
+    /// Allocates a variable in the innermost scope: that of the running function, or the globals.
+    fn declare_variable(&mut self, name: &str, ty: &GrammarVartype) -> usize {
+        let address = self.memory.allocate(ty.size(),ty.align());
+        let variable = Variable { address, ty: ty.clone() };
+        match self.frames.last_mut() {
+            Some(frame) => frame.variables.declare(name.to_string(),variable),
+            None => self.globals.declare(name.to_string(),variable),
+        };
+        address
+    }
+    /// Finds a variable in the scopes of the running function, then in the globals. A missing one
+    /// stops the program and stands at the null pointer, so using it does nothing more.
+    fn lookup_variable(&mut self, name: &str) -> Variable {
+        let local = self.frames.last().and_then(|frame| frame.variables.lookup(name));
+        if let Some(variable) = local.or_else(|| self.globals.lookup(name)) {
+            return variable.clone();
+        }
+        self.fail(format!("Variable not declared: {}",name));
+        Variable { address: 0, ty: GrammarVartype::int() }
+    }
+    fn push_scope(&mut self) {
+        let mark = self.memory.mark();
+        if let Some(frame) = self.frames.last_mut() {
+            frame.variables.push();
+            frame.marks.push(mark);
+        }
+    }
+    /// Leaves a block scope, freeing the variables declared in it.
+    fn pop_scope(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.variables.pop();
+            if let Some(mark) = frame.marks.pop() {
+                self.memory.release(mark);
+            }
+        }
+    }
+    /// The value of the object at `address`. Arrays, records and functions are used through their address.
+    fn load(&mut self, address: usize, ty: &GrammarVartype) -> Value {
+        if ty.is_aggregate() || matches!(ty,GrammarVartype::Function(_)) {
+            return Value::Integer(address as i64);
+        }
+        let bits = self.memory.load(address,ty.size()).unwrap_or_else(|| {
+            self.fail_access(address);
+            0
+        });
+        Value::from_bits(bits,ty)
+    }
+    /// Stores `value` into the object at `address`; a record is copied from the one `value` addresses.
+    fn store(&mut self, address: usize, ty: &GrammarVartype, value: Value) {
+        let stored = if ty.is_record() {
+            self.memory.copy(address,value.as_integer() as usize,ty.size())
+        } else {
+            self.memory.store(address,ty.size(),value.to_bits(ty))
+        };
+        if stored.is_none() {
+            self.fail_access(address);
+        }
+    }
+    fn string_address(&mut self, string: &ASTStringExpression) -> usize {
+        *self.strings.entry(string.value.clone()).or_insert_with(|| self.memory.allocate_static(&string.value))
+    }
+    fn member_address(&mut self, member: &ASTMemberExpression) -> usize {
+        let base = self.evaluate_address(&member.base);
+        self.location = Some(member.span.clone());
+        base.wrapping_add(member.offset)
+    }
+    /// The object `*operand` designates.
+    fn dereference(&mut self, unary: &ASTUnaryExpression) -> Variable {
+        let address = self.evaluate_address(&unary.operand);
+        self.location = Some(unary.operator.token().span.clone());
+        let ty = unary.operand.ty.decay().pointee().expect("Dereference of a non-pointer").clone();
+        Variable { address, ty }
+    }
+    /// Applies a binary operator, stopping the program when it divides by zero.
+    fn apply_binary_operator(&mut self, operator: &ASTBinaryOperator, left: Value, left_type: &GrammarVartype, right: Value, right_type: &GrammarVartype) -> Value {
+        self.location = Some(operator.token().span.clone());
+        operator.evaluate_value(left,left_type,right,right_type).unwrap_or_else(|| {
+            let message = if right.as_integer() == 0 { "Division by zero" } else { "Integer overflow in division" };
+            self.fail(message.to_string());
+            Value::Integer(0)
+        })
+    }
+    /// Stops the program with a run-time error at the operator or call evaluated last. Only the
+    /// first error is kept, as the values computed after it are meaningless.
+    fn fail(&mut self, message: String) {
+        if self.fault.is_none() {
+            let span = self.location.clone().unwrap_or_else(|| TextSpan::new(0,0,String::new()));
+            self.fault = Some(Diagnostic::new(message,span,DiagnosticKind::Error));
+        }
+    }
+    fn fail_access(&mut self, address: usize) {
+        if address < Memory::RESERVED {
+            self.fail("Dereference of a null pointer".to_string());
+        } else {
+            self.fail(format!("Invalid memory access at address {}",address));
+        }
+    }
+    /// The object an lvalue designates.
+    fn left_value_object(&mut self, left_value: &LeftValue) -> Variable {
+        match left_value {
+            LeftValue::Variable(name) => self.lookup_variable(name),
+            LeftValue::Deref(unary) => self.dereference(unary),
+            LeftValue::Member(member) => Variable { address: self.member_address(member), ty: member.ty.clone() },
+        }
+    }
+    /// The address of the object `expr` designates, for the `&` operator.
+    fn expression_address(&mut self, expr: &ASTExpression) -> usize {
+        match &expr.kind {
+            ASTExpressionKind::Variable(variable) => self.lookup_variable(&variable.name).address,
+            ASTExpressionKind::Unary(unary) => self.evaluate_address(&unary.operand),
+            ASTExpressionKind::Member(member) => self.member_address(member),
+            ASTExpressionKind::String(string) => self.string_address(string),
+            _ => {
+                self.fail("Cannot take the address of an rvalue".to_string());
+                0
+            }
+        }
+    }
+    /// Calls `function` in a new frame, returning the value of its `return` statement.
+    fn visit_intepret_function(&mut self, function: &ASTFunction, arguments: Vec<Value>) -> Option<Value> {
+        let base = self.memory.mark();
+        let returntype = function.function_type.returntype.clone();
+        self.frames.push(Frame { variables: ScopeStack::new(), marks: Vec::new(), base, returntype });
+        for (param,arg) in function.params.iter().zip(arguments) {
+            let address = self.declare_variable(&param.name,&param.param_type);
+            self.store(address,&param.param_type,arg);
+        }
+        self.visit_statements(&function.statements);
+        let result = match std::mem::replace(&mut self.control, ControlFlow::Normal) {
+            ControlFlow::Return(value) => value,
+            _ => None,
+        };
+        // A returned record lives in the frame about to be freed, so it moves to a temporary of the caller.
+        let returntype = &function.function_type.returntype;
+        let record = result.filter(|_| returntype.is_record())
+            .and_then(|address| self.memory.read(address.as_integer() as usize,returntype.size()));
+        if let Some(frame) = self.frames.pop() {
+            self.memory.release(frame.base);
+        }
+        match record {
+            Some(bytes) => {
+                let address = self.memory.allocate(returntype.size(),returntype.align());
+                self.memory.write(address,&bytes)?;
+                Some(Value::Integer(address as i64))
             }
+            None => result,
         }
-        function.statements.iter().for_each(|x| self.visit_statement(x));
     }
 }
\ No newline at end of file
@@ -1,42 +1,135 @@
-use std::fmt::Display;
-use crate::ast::LeftValue;
-use crate::ast::lexer::{TextSpan, Token};
+use crate::ast::{GrammarMember, GrammarVartype, LeftValue};
+use crate::ast::lexer::{TextSpan, Token, TokenKind, VartypeKind};
+use crate::ast::value::Value;
 #[derive(Debug,Clone)]
 pub enum ASTExpressionKind{
     Number(ASTNumberExpression),
+    String(ASTStringExpression),
     Variable(ASTVariableExpression),
     Binary(ASTBinaryExpression),
+    Unary(ASTUnaryExpression),
+    Cast(ASTCastExpression),
+    Conditional(ASTConditionalExpression),
+    Member(ASTMemberExpression),
     Error(TextSpan),
     Assignment(ASTAssignment),
+    Increment(ASTIncrementExpression),
     FunctionCall(ASTFuncionCall),
 }
 
 #[derive(Debug,Clone)]
 pub struct ASTExpression{
-    pub(crate) kind:ASTExpressionKind
+    pub(crate) kind:ASTExpressionKind,
+    /// The static type of the value, worked out by the parser.
+    pub(crate) ty:GrammarVartype,
 }
 
 impl ASTExpression {
-    pub fn new(kind:ASTExpressionKind) -> Self{
-        ASTExpression {kind}
+    pub fn new(kind:ASTExpressionKind,ty:GrammarVartype) -> Self{
+        ASTExpression {kind,ty}
     }
     pub fn number(number:i64) -> Self{
-        ASTExpression::new(ASTExpressionKind::Number(ASTNumberExpression::new(number)))
+        ASTExpression::typed_number(number,GrammarVartype::int())
+    }
+    pub fn typed_number(number:i64,ty:GrammarVartype) -> Self{
+        ASTExpression::new(ASTExpressionKind::Number(ASTNumberExpression::new(Value::Integer(number))),ty)
+    }
+    pub fn floating(number:f64,ty:GrammarVartype) -> Self{
+        ASTExpression::new(ASTExpressionKind::Number(ASTNumberExpression::new(Value::Floating(number))),ty)
+    }
+    /// A string literal, an array of `char` holding `value` and the terminating NUL.
+    pub fn string(mut value:Vec<u8>) -> Self{
+        value.push(0);
+        let ty = GrammarVartype::array(GrammarVartype::Direct(VartypeKind::Char),value.len());
+        ASTExpression::new(ASTExpressionKind::String(ASTStringExpression{value}),ty)
     }
     pub fn binary( operator:ASTBinaryOperator,left:ASTExpression,right:ASTExpression) -> Self{
-        ASTExpression::new(ASTExpressionKind::Binary(ASTBinaryExpression::new(left,right,operator)))
+        let ty = operator.result_type(&left.ty,&right.ty);
+        ASTExpression::new(ASTExpressionKind::Binary(ASTBinaryExpression::new(left,right,operator)),ty)
+    }
+    pub fn unary(operator:ASTUnaryOperator,operand:ASTExpression) -> Self{
+        let ty = operator.result_type(&operand.ty);
+        ASTExpression::new(ASTExpressionKind::Unary(ASTUnaryExpression::new(operator,operand)),ty)
+    }
+    pub fn cast(ty:GrammarVartype,operand:ASTExpression) -> Self{
+        ASTExpression::new(ASTExpressionKind::Cast(ASTCastExpression::new(operand)),ty)
+    }
+    pub fn conditional(condition:ASTExpression,then_expr:ASTExpression,else_expr:ASTExpression) -> Self{
+        let is_arithmetic = |ty:&GrammarVartype| ty.is_integer() || ty.is_floating();
+        let ty = if is_arithmetic(&then_expr.ty) && is_arithmetic(&else_expr.ty) {
+            then_expr.ty.common(&else_expr.ty)
+        } else if !then_expr.ty.decay().is_pointer() {
+            // A null pointer constant takes the type of the pointer on the other side.
+            else_expr.ty.decay()
+        } else {
+            then_expr.ty.decay()
+        };
+        ASTExpression::new(ASTExpressionKind::Conditional(ASTConditionalExpression::new(condition,then_expr,else_expr)),ty)
+    }
+    pub fn member(base:ASTExpression,member:GrammarMember,token:Token) -> Self{
+        let ty = member.ty.clone();
+        ASTExpression::new(ASTExpressionKind::Member(ASTMemberExpression::new(base,member,token)),ty)
     }
-    pub fn variable(name:String) -> Self{
-        ASTExpression::new(ASTExpressionKind::Variable(ASTVariableExpression::new(name)))
+    pub fn variable(name:String,ty:GrammarVartype) -> Self{
+        ASTExpression::new(ASTExpressionKind::Variable(ASTVariableExpression::new(name)),ty)
     }
     pub fn assignment(name:ASTExpression,expr:ASTExpression) -> Self{
-        ASTExpression::new(ASTExpressionKind::Assignment(ASTAssignment::new(name,expr)))
+        let ty = name.ty.clone();
+        ASTExpression::new(ASTExpressionKind::Assignment(ASTAssignment::new(name,expr,None)),ty)
     }
-    pub fn function_call(name:String,param_list:Vec<Box<ASTExpression>>) -> Self{
-        ASTExpression::new(ASTExpressionKind::FunctionCall(ASTFuncionCall::new(name,param_list)))
+    pub fn compound_assignment(name:ASTExpression,operator:ASTBinaryOperator,expr:ASTExpression) -> Self{
+        let ty = name.ty.clone();
+        ASTExpression::new(ASTExpressionKind::Assignment(ASTAssignment::new(name,expr,Some(operator))),ty)
+    }
+    pub fn increment(name:ASTExpression,kind:ASTIncrementKind,postfix:bool) -> Self{
+        let ty = name.ty.clone();
+        ASTExpression::new(ASTExpressionKind::Increment(ASTIncrementExpression::new(name,kind,postfix)),ty)
+    }
+    pub fn function_call(callee:ASTExpression,param_list:Vec<ASTExpression>,paren:Token,ty:GrammarVartype) -> Self{
+        ASTExpression::new(ASTExpressionKind::FunctionCall(ASTFuncionCall::new(callee,param_list,paren)),ty)
     }
     pub fn error(span:TextSpan) -> Self {
-        ASTExpression::new(ASTExpressionKind::Error(span))
+        ASTExpression::new(ASTExpressionKind::Error(span),GrammarVartype::int())
+    }
+    /// Folds an integer constant expression, `None` when it is not one. Floating constants
+    /// only take part as the immediate operand of a cast to an integer type.
+    pub fn constant_value(&self) -> Option<i64> {
+        match &self.kind {
+            ASTExpressionKind::Number(number) => match number.value {
+                Value::Integer(value) => Some(value),
+                Value::Floating(_) => None,
+            },
+            ASTExpressionKind::Binary(binary) => {
+                let left = binary.left.constant_value()?;
+                if let Some(value) = binary.operator.short_circuit(left) {
+                    return Some(value);
+                }
+                let right = binary.right.constant_value()?;
+                binary.operator.evaluate_typed(left,&binary.left.ty,right,&binary.right.ty)
+            }
+            ASTExpressionKind::Unary(unary) => {
+                let operand = unary.operand.constant_value()?;
+                unary.operator.evaluate_typed(operand,&unary.operand.ty)
+            }
+            ASTExpressionKind::Cast(_) if !self.ty.is_integer() => None,
+            ASTExpressionKind::Cast(cast) => match &cast.operand.kind {
+                ASTExpressionKind::Number(number) => Some(number.value.convert(&cast.operand.ty,&self.ty).as_integer()),
+                _ => Some(self.ty.convert(cast.operand.constant_value()?)),
+            },
+            ASTExpressionKind::Conditional(conditional) => {
+                let value = if conditional.condition.constant_value()? != 0 {
+                    conditional.then_expr.constant_value()
+                } else {
+                    conditional.else_expr.constant_value()
+                };
+                value.map(|value| self.ty.convert(value))
+            }
+            _ => None,
+        }
+    }
+    /// Whether this is an integer constant expression with the value 0, which converts to any pointer type.
+    pub fn is_null_pointer_constant(&self) -> bool {
+        self.ty.is_integer() && self.constant_value() == Some(0)
     }
 }
 
@@ -53,18 +146,23 @@ impl ASTVariableExpression {
 
 #[derive(Debug,Clone)]
 pub struct ASTNumberExpression{
-    pub(crate) value:i64
+    pub(crate) value:Value
 }
 
 impl ASTNumberExpression {
-    pub fn new(value:i64) -> Self {
+    pub fn new(value:Value) -> Self {
         ASTNumberExpression { value }
     }
-    pub fn value(&self) -> i64 {
+    pub fn value(&self) -> Value {
         self.value
     }
 }
 
+#[derive(Debug,Clone)]
+pub struct ASTStringExpression{
+    /// The bytes of the array, the terminating NUL included.
+    pub(crate) value:Vec<u8>
+}
 
 #[derive(Debug,Clone)]
 pub enum ASTBinaryOperatorKind{
@@ -72,9 +170,29 @@ pub enum ASTBinaryOperatorKind{
     Sub,
     Mul,
     Div,
-    Equal
+    Mod,
+    ShiftLeft,
+    ShiftRight,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    EqualEqual,
+    NotEqual,
+    BitwiseAnd,
+    BitwiseXor,
+    BitwiseOr,
+    LogicalAnd,
+    LogicalOr,
+    Equal,
+    Comma,
 }
 
+/// Precedence of `?:`, between assignment and `||`.
+pub const CONDITIONAL_PRECEDENCE: u8 = 3;
+/// Precedence of the assignment operators, the lowest an operand of `,` may use.
+pub const ASSIGNMENT_PRECEDENCE: u8 = 2;
+
 #[derive(Debug,Clone)]
 pub struct ASTBinaryOperator{
     pub(crate) kind:ASTBinaryOperatorKind,
@@ -85,21 +203,192 @@ impl ASTBinaryOperator {
     pub fn new(kind:ASTBinaryOperatorKind,token:Token) -> Self{
         ASTBinaryOperator { kind, token }
     }
+    pub fn token(&self) -> &Token{
+        &self.token
+    }
+    /// Binding strength following the C precedence table, higher binds tighter.
     pub fn precedence(&self) -> u8{
         match self.kind{
-            ASTBinaryOperatorKind::Add => 2,
-            ASTBinaryOperatorKind::Sub => 2,
-            ASTBinaryOperatorKind::Mul => 3,
-            ASTBinaryOperatorKind::Div => 3,
-            ASTBinaryOperatorKind::Equal => 1,
+            ASTBinaryOperatorKind::Mul => 13,
+            ASTBinaryOperatorKind::Div => 13,
+            ASTBinaryOperatorKind::Mod => 13,
+            ASTBinaryOperatorKind::Add => 12,
+            ASTBinaryOperatorKind::Sub => 12,
+            ASTBinaryOperatorKind::ShiftLeft => 11,
+            ASTBinaryOperatorKind::ShiftRight => 11,
+            ASTBinaryOperatorKind::Less => 10,
+            ASTBinaryOperatorKind::LessEqual => 10,
+            ASTBinaryOperatorKind::Greater => 10,
+            ASTBinaryOperatorKind::GreaterEqual => 10,
+            ASTBinaryOperatorKind::EqualEqual => 9,
+            ASTBinaryOperatorKind::NotEqual => 9,
+            ASTBinaryOperatorKind::BitwiseAnd => 8,
+            ASTBinaryOperatorKind::BitwiseXor => 7,
+            ASTBinaryOperatorKind::BitwiseOr => 6,
+            ASTBinaryOperatorKind::LogicalAnd => 5,
+            ASTBinaryOperatorKind::LogicalOr => 4,
+            ASTBinaryOperatorKind::Equal => ASSIGNMENT_PRECEDENCE,
+            ASTBinaryOperatorKind::Comma => 1,
         }
     }
-    pub fn right_combined(&self) -> bool{
+    /// The type of the result: pointer arithmetic keeps the pointer type and the difference
+    /// of two pointers is a `long`, comparisons and logical operators give `int`, shifts the
+    /// promoted left operand and other arithmetic the common type of the operands.
+    pub fn result_type(&self,left:&GrammarVartype,right:&GrammarVartype) -> GrammarVartype{
+        let (left,right) = (left.decay(),right.decay());
+        match self.kind {
+            ASTBinaryOperatorKind::Add if right.is_pointer() => right,
+            ASTBinaryOperatorKind::Sub if left.is_pointer() && right.is_pointer() => GrammarVartype::Direct(VartypeKind::Long),
+            ASTBinaryOperatorKind::Less | ASTBinaryOperatorKind::LessEqual |
+            ASTBinaryOperatorKind::Greater | ASTBinaryOperatorKind::GreaterEqual |
+            ASTBinaryOperatorKind::EqualEqual | ASTBinaryOperatorKind::NotEqual |
+            ASTBinaryOperatorKind::LogicalAnd | ASTBinaryOperatorKind::LogicalOr => GrammarVartype::int(),
+            ASTBinaryOperatorKind::Comma => right,
+            ASTBinaryOperatorKind::ShiftLeft | ASTBinaryOperatorKind::ShiftRight => left.promote(),
+            _ => left.common(&right),
+        }
+    }
+    /// Like [`ASTBinaryOperator::evaluate`], with C pointer arithmetic: an integer added to
+    /// or subtracted from a pointer counts elements, and so does the difference of two pointers.
+    /// Integer operands are converted to their common type first and the result wraps to its width.
+    pub fn evaluate_typed(&self,left:i64,left_type:&GrammarVartype,right:i64,right_type:&GrammarVartype) -> Option<i64>{
+        let (left_type,right_type) = (&left_type.decay(),&right_type.decay());
+        let element_size = |pointer:&GrammarVartype| pointer.pointee().map_or(1, |pointee| pointee.size() as i64);
+        match (&self.kind,left_type.is_pointer(),right_type.is_pointer()) {
+            (ASTBinaryOperatorKind::Add,true,false) => Some(left.wrapping_add(right.wrapping_mul(element_size(left_type)))),
+            (ASTBinaryOperatorKind::Add,false,true) => Some(right.wrapping_add(left.wrapping_mul(element_size(right_type)))),
+            (ASTBinaryOperatorKind::Sub,true,false) => Some(left.wrapping_sub(right.wrapping_mul(element_size(left_type)))),
+            (ASTBinaryOperatorKind::Sub,true,true) => Some(left.wrapping_sub(right) / element_size(left_type)),
+            _ if left_type.is_integer() && right_type.is_integer() => {
+                let (left,right) = match self.kind {
+                    ASTBinaryOperatorKind::ShiftLeft | ASTBinaryOperatorKind::ShiftRight => (left_type.promote().convert(left),right),
+                    ASTBinaryOperatorKind::LogicalAnd | ASTBinaryOperatorKind::LogicalOr |
+                    ASTBinaryOperatorKind::Equal | ASTBinaryOperatorKind::Comma => (left,right),
+                    _ => {
+                        let common = left_type.common(right_type);
+                        (common.convert(left),common.convert(right))
+                    }
+                };
+                let operand_type = self.result_type(left_type,right_type);
+                let unsigned = match self.kind {
+                    ASTBinaryOperatorKind::ShiftLeft | ASTBinaryOperatorKind::ShiftRight => left_type.promote().is_unsigned(),
+                    _ => left_type.common(right_type).is_unsigned(),
+                };
+                let value = if unsigned { self.evaluate_unsigned(left as u64,right as u64)? } else { self.evaluate(left,right)? };
+                Some(operand_type.convert(value))
+            }
+            _ => self.evaluate(left,right),
+        }
+    }
+    /// Like [`ASTBinaryOperator::evaluate_typed`], for operands that may be floating: when
+    /// their common type is floating the operation follows IEEE 754 arithmetic, rounded to
+    /// that type, and `None` means the operator does not apply to floating operands.
+    pub fn evaluate_value(&self,left:Value,left_type:&GrammarVartype,right:Value,right_type:&GrammarVartype) -> Option<Value>{
+        let common = left_type.common(right_type);
+        match self.kind {
+            ASTBinaryOperatorKind::Equal | ASTBinaryOperatorKind::Comma => Some(right),
+            ASTBinaryOperatorKind::LogicalAnd => Some(Value::Integer((left.is_true() && right.is_true()) as i64)),
+            ASTBinaryOperatorKind::LogicalOr => Some(Value::Integer((left.is_true() || right.is_true()) as i64)),
+            _ if common.is_floating() => {
+                let left = left.convert(left_type,&common).as_floating();
+                let right = right.convert(right_type,&common).as_floating();
+                let value = match self.kind {
+                    ASTBinaryOperatorKind::Add => left + right,
+                    ASTBinaryOperatorKind::Sub => left - right,
+                    ASTBinaryOperatorKind::Mul => left * right,
+                    ASTBinaryOperatorKind::Div => left / right,
+                    ASTBinaryOperatorKind::Less => return Some(Value::Integer((left < right) as i64)),
+                    ASTBinaryOperatorKind::LessEqual => return Some(Value::Integer((left <= right) as i64)),
+                    ASTBinaryOperatorKind::Greater => return Some(Value::Integer((left > right) as i64)),
+                    ASTBinaryOperatorKind::GreaterEqual => return Some(Value::Integer((left >= right) as i64)),
+                    ASTBinaryOperatorKind::EqualEqual => return Some(Value::Integer((left == right) as i64)),
+                    ASTBinaryOperatorKind::NotEqual => return Some(Value::Integer((left != right) as i64)),
+                    _ => return None,
+                };
+                // Double precision is wide enough for rounding its result to `float` to be correct as well.
+                Some(Value::Floating(value).convert(&common,&common))
+            }
+            _ => self.evaluate_typed(left.as_integer(),left_type,right.as_integer(),right_type).map(Value::Integer),
+        }
+    }
+    /// Whether the operator applies to these operands: `*` and `/` need arithmetic operands and
+    /// `%`, shifts and bitwise operators integers; only integers are added to or subtracted from
+    /// pointers to objects, and only pointers of the same type subtracted from each other;
+    /// pointers compare with pointers, and for equality with null pointer constants; `&&` and
+    /// `||` need scalars, and records are only operands of the comma operator.
+    pub fn accepts(&self,left:&ASTExpression,right:&ASTExpression) -> bool{
+        let (left_ty,right_ty) = (left.ty.decay(),right.ty.decay());
+        let arithmetic = left_ty.is_arithmetic() && right_ty.is_arithmetic();
+        let is_object_pointer = |ty:&GrammarVartype| ty.pointee().is_some_and(|pointee| !matches!(pointee,GrammarVartype::Function(_)));
+        match self.kind {
+            ASTBinaryOperatorKind::Mul | ASTBinaryOperatorKind::Div => arithmetic,
+            ASTBinaryOperatorKind::Mod | ASTBinaryOperatorKind::ShiftLeft | ASTBinaryOperatorKind::ShiftRight |
+            ASTBinaryOperatorKind::BitwiseAnd | ASTBinaryOperatorKind::BitwiseXor | ASTBinaryOperatorKind::BitwiseOr => left_ty.is_integer() && right_ty.is_integer(),
+            ASTBinaryOperatorKind::Add => {
+                arithmetic || (is_object_pointer(&left_ty) && right_ty.is_integer()) || (left_ty.is_integer() && is_object_pointer(&right_ty))
+            }
+            ASTBinaryOperatorKind::Sub => arithmetic || (is_object_pointer(&left_ty) && (right_ty.is_integer() || left_ty == right_ty)),
+            ASTBinaryOperatorKind::Less | ASTBinaryOperatorKind::LessEqual |
+            ASTBinaryOperatorKind::Greater | ASTBinaryOperatorKind::GreaterEqual => arithmetic || (left_ty.is_pointer() && right_ty.is_pointer()),
+            ASTBinaryOperatorKind::EqualEqual | ASTBinaryOperatorKind::NotEqual => {
+                arithmetic
+                    || (left_ty.is_pointer() && (right_ty.is_pointer() || right.is_null_pointer_constant()))
+                    || (right_ty.is_pointer() && left.is_null_pointer_constant())
+            }
+            ASTBinaryOperatorKind::LogicalAnd | ASTBinaryOperatorKind::LogicalOr => left_ty.is_scalar() && right_ty.is_scalar(),
+            ASTBinaryOperatorKind::Equal | ASTBinaryOperatorKind::Comma => true,
+        }
+    }
+    /// Applies the operator to unsigned operands, which only changes division, shifts and comparisons.
+    fn evaluate_unsigned(&self,left:u64,right:u64) -> Option<i64>{
         match self.kind {
-            ASTBinaryOperatorKind::Equal => true,
-            _ => false
+            ASTBinaryOperatorKind::Div => left.checked_div(right).map(|value| value as i64),
+            ASTBinaryOperatorKind::Mod => left.checked_rem(right).map(|value| value as i64),
+            ASTBinaryOperatorKind::ShiftRight => Some(left.wrapping_shr(right as u32) as i64),
+            ASTBinaryOperatorKind::Less => Some((left < right) as i64),
+            ASTBinaryOperatorKind::LessEqual => Some((left <= right) as i64),
+            ASTBinaryOperatorKind::Greater => Some((left > right) as i64),
+            ASTBinaryOperatorKind::GreaterEqual => Some((left >= right) as i64),
+            _ => self.evaluate(left as i64,right as i64),
         }
     }
+    /// The result of `&&` and `||` when the left operand alone decides it,
+    /// in which case the right operand must not be evaluated.
+    pub fn short_circuit(&self,left:i64) -> Option<i64>{
+        match self.kind {
+            ASTBinaryOperatorKind::LogicalAnd if left == 0 => Some(0),
+            ASTBinaryOperatorKind::LogicalOr if left != 0 => Some(1),
+            _ => None,
+        }
+    }
+    /// Applies the operator to integer operands, `None` on division by zero.
+    pub fn evaluate(&self,left:i64,right:i64) -> Option<i64>{
+        match self.kind {
+            ASTBinaryOperatorKind::Add => Some(left.wrapping_add(right)),
+            ASTBinaryOperatorKind::Sub => Some(left.wrapping_sub(right)),
+            ASTBinaryOperatorKind::Mul => Some(left.wrapping_mul(right)),
+            ASTBinaryOperatorKind::Div => left.checked_div(right),
+            ASTBinaryOperatorKind::Mod => left.checked_rem(right),
+            ASTBinaryOperatorKind::ShiftLeft => Some(left.wrapping_shl(right as u32)),
+            ASTBinaryOperatorKind::ShiftRight => Some(left.wrapping_shr(right as u32)),
+            ASTBinaryOperatorKind::Less => Some((left < right) as i64),
+            ASTBinaryOperatorKind::LessEqual => Some((left <= right) as i64),
+            ASTBinaryOperatorKind::Greater => Some((left > right) as i64),
+            ASTBinaryOperatorKind::GreaterEqual => Some((left >= right) as i64),
+            ASTBinaryOperatorKind::EqualEqual => Some((left == right) as i64),
+            ASTBinaryOperatorKind::NotEqual => Some((left != right) as i64),
+            ASTBinaryOperatorKind::BitwiseAnd => Some(left & right),
+            ASTBinaryOperatorKind::BitwiseXor => Some(left ^ right),
+            ASTBinaryOperatorKind::BitwiseOr => Some(left | right),
+            ASTBinaryOperatorKind::LogicalAnd => Some((left != 0 && right != 0) as i64),
+            ASTBinaryOperatorKind::LogicalOr => Some((left != 0 || right != 0) as i64),
+            ASTBinaryOperatorKind::Equal => Some(right),
+            ASTBinaryOperatorKind::Comma => Some(right),
+        }
+    }
+    pub fn right_combined(&self) -> bool{
+        matches!(self.kind, ASTBinaryOperatorKind::Equal)
+    }
 }
 
 #[derive(Debug,Clone)]
@@ -120,29 +409,200 @@ impl ASTBinaryExpression {
 }
 
 
+#[derive(Debug,Clone)]
+pub enum ASTUnaryOperatorKind{
+    Negate,
+    Plus,
+    LogicalNot,
+    BitwiseNot,
+    AddressOf,
+    Dereference,
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTUnaryOperator{
+    pub(crate) kind:ASTUnaryOperatorKind,
+    token:Token,
+}
+
+impl ASTUnaryOperator {
+    pub fn new(kind:ASTUnaryOperatorKind,token:Token) -> Self{
+        ASTUnaryOperator { kind, token }
+    }
+    pub fn token(&self) -> &Token{
+        &self.token
+    }
+    pub fn result_type(&self,operand:&GrammarVartype) -> GrammarVartype{
+        match self.kind {
+            ASTUnaryOperatorKind::AddressOf => GrammarVartype::reference(operand.clone()),
+            ASTUnaryOperatorKind::Dereference => operand.decay().pointee().cloned().unwrap_or_else(GrammarVartype::int),
+            ASTUnaryOperatorKind::LogicalNot => GrammarVartype::int(),
+            _ => operand.promote(),
+        }
+    }
+    /// Like [`ASTUnaryOperator::evaluate`], wrapping the result to the width of the promoted operand.
+    pub fn evaluate_typed(&self,operand:i64,operand_type:&GrammarVartype) -> Option<i64>{
+        let ty = self.result_type(operand_type);
+        self.evaluate(ty.convert(operand)).map(|value| ty.convert(value))
+    }
+    /// Like [`ASTUnaryOperator::evaluate_typed`], for an operand that may be floating.
+    pub fn evaluate_value(&self,operand:Value,operand_type:&GrammarVartype) -> Option<Value>{
+        if !operand_type.promote().is_floating() {
+            return self.evaluate_typed(operand.as_integer(),operand_type).map(Value::Integer);
+        }
+        let operand = operand.as_floating();
+        match self.kind {
+            ASTUnaryOperatorKind::Negate => Some(Value::Floating(-operand)),
+            ASTUnaryOperatorKind::Plus => Some(Value::Floating(operand)),
+            ASTUnaryOperatorKind::LogicalNot => Some(Value::Integer((operand == 0.0) as i64)),
+            _ => None,
+        }
+    }
+    /// Applies an arithmetic operator to an integer operand, `None` for `&` and `*`,
+    /// which work on objects rather than values.
+    pub fn evaluate(&self,operand:i64) -> Option<i64>{
+        match self.kind {
+            ASTUnaryOperatorKind::Negate => Some(operand.wrapping_neg()),
+            ASTUnaryOperatorKind::Plus => Some(operand),
+            ASTUnaryOperatorKind::LogicalNot => Some((operand == 0) as i64),
+            ASTUnaryOperatorKind::BitwiseNot => Some(!operand),
+            ASTUnaryOperatorKind::AddressOf | ASTUnaryOperatorKind::Dereference => None,
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTUnaryExpression{
+    pub(crate) operator:ASTUnaryOperator,
+    pub(crate) operand: Box<ASTExpression>,
+}
+
+impl ASTUnaryExpression {
+    fn new(operator:ASTUnaryOperator,operand:ASTExpression) -> Self{
+        ASTUnaryExpression {
+            operator,
+            operand: Box::new(operand),
+        }
+    }
+}
+
+/// `(type) operand`; the target type is the type of the whole expression.
+#[derive(Debug,Clone)]
+pub struct ASTCastExpression{
+    pub(crate) operand: Box<ASTExpression>,
+}
+
+impl ASTCastExpression {
+    fn new(operand:ASTExpression) -> Self{
+        ASTCastExpression {
+            operand: Box::new(operand),
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTConditionalExpression{
+    pub(crate) condition: Box<ASTExpression>,
+    pub(crate) then_expr: Box<ASTExpression>,
+    pub(crate) else_expr: Box<ASTExpression>,
+}
+
+impl ASTConditionalExpression {
+    fn new(condition:ASTExpression,then_expr:ASTExpression,else_expr:ASTExpression) -> Self{
+        ASTConditionalExpression {
+            condition: Box::new(condition),
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr),
+        }
+    }
+}
+
+/// Access to a member of a struct or union, through a pointer to it for `->`.
+#[derive(Debug,Clone)]
+pub struct ASTMemberExpression{
+    pub(crate) base: Box<ASTExpression>,
+    pub(crate) name: String,
+    pub(crate) ty: GrammarVartype,
+    pub(crate) offset: usize,
+    pub(crate) arrow: bool,
+    /// Where the `.` or `->` is.
+    pub(crate) span: TextSpan,
+}
+
+impl ASTMemberExpression {
+    fn new(base:ASTExpression,member:GrammarMember,token:Token) -> Self{
+        ASTMemberExpression {
+            base: Box::new(base),
+            name: member.name,
+            ty: member.ty,
+            offset: member.offset,
+            arrow: token.kind == TokenKind::Arrow,
+            span: token.span,
+        }
+    }
+}
 
 #[derive(Debug,Clone)]
 pub struct ASTAssignment{
     pub(crate) name: LeftValue,
     pub(crate) expr: Box<ASTExpression>,
+    /// The operator of a compound assignment such as `+=`, applied to the old value and `expr`.
+    pub(crate) operator: Option<ASTBinaryOperator>,
 }
 
 impl ASTAssignment{
-    pub fn new(name:ASTExpression,expr:ASTExpression) -> Self{
+    pub fn new(name:ASTExpression,expr:ASTExpression,operator:Option<ASTBinaryOperator>) -> Self{
         ASTAssignment {
-            name: LeftValue::variable(name),
+            name: LeftValue::new(name),
             expr: Box::new(expr),
+            operator,
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub enum ASTIncrementKind{
+    Increment,
+    Decrement,
+}
+
+impl ASTIncrementKind {
+    pub fn delta(&self) -> i64{
+        match self {
+            ASTIncrementKind::Increment => 1,
+            ASTIncrementKind::Decrement => -1,
+        }
+    }
+}
+
+/// `++` or `--` before or after an lvalue; the postfix forms yield the old value.
+#[derive(Debug,Clone)]
+pub struct ASTIncrementExpression{
+    pub(crate) name: LeftValue,
+    pub(crate) kind: ASTIncrementKind,
+    pub(crate) postfix: bool,
+}
+
+impl ASTIncrementExpression {
+    pub fn new(name:ASTExpression,kind:ASTIncrementKind,postfix:bool) -> Self{
+        ASTIncrementExpression {
+            name: LeftValue::new(name),
+            kind,
+            postfix,
         }
     }
 }
 
 #[derive(Debug,Clone)]
 pub struct ASTFuncionCall{
-    pub(crate) name: String,
-    pub(crate) param_list: Vec<Box<ASTExpression>>,
+    /// The function called, or a pointer to it.
+    pub(crate) callee: Box<ASTExpression>,
+    pub(crate) param_list: Vec<ASTExpression>,
+    /// The `(` token opening the arguments.
+    pub(crate) paren: Token,
 }
 impl ASTFuncionCall {
-    pub fn new(name:String,param_list:Vec<Box<ASTExpression>>) -> Self {
-        ASTFuncionCall { name, param_list }
+    pub fn new(callee:ASTExpression,param_list:Vec<ASTExpression>,paren:Token) -> Self {
+        ASTFuncionCall { callee: Box::new(callee), param_list, paren }
     }
 }
\ No newline at end of file
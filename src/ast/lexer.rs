@@ -1,9 +1,23 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fmt::{write, Debug, Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+use crate::diagnostics::{DiagnosticBag, DiagnosticsBagCell};
 
 #[derive(Debug,PartialEq,Clone)]
 pub enum VartypeKind{
+    /// Plain `char`, which is signed like `signed char` but a type of its own.
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
     Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
     Float,
     Double
 }
@@ -11,10 +25,38 @@ pub enum VartypeKind{
 #[derive(Debug,PartialEq,Clone)]
 pub enum TokenKind {
     Number(i64),
+    FloatNumber(f64),
     Plus,
+    PlusPlus,
+    PlusEqual,
     Minus,
+    MinusMinus,
+    MinusEqual,
     Asterisk,
+    AsteriskEqual,
     Slash,
+    SlashEqual,
+    Percent,
+    PercentEqual,
+    Bang,
+    BangEqual,
+    Tilde,
+    Ampersand,
+    AmpersandAmpersand,
+    AmpersandEqual,
+    Pipe,
+    PipePipe,
+    PipeEqual,
+    Caret,
+    CaretEqual,
+    Less,
+    LessEqual,
+    LessLess,
+    LessLessEqual,
+    Greater,
+    GreaterEqual,
+    GreaterGreater,
+    GreaterGreaterEqual,
     LeftParen,
     RightParen,
     LeftBracket,
@@ -22,12 +64,45 @@ pub enum TokenKind {
     LeftBrace,
     RightBrace,
     Equal,
+    EqualEqual,
+    Colon,
+    Question,
+    Dot,
+    Arrow,
     SemiColon,
     WhiteSpace,
+    /// A `//` or `/* */` comment, which separates tokens like whitespace.
+    Comment,
     Comma,
+    Hash,
+    HashHash,
+    /// The value of a character constant, as `'a'` or `'\n'`.
+    CharLiteral(i64),
+    /// The bytes a string literal stands for, escape sequences replaced and without the closing NUL.
+    StringLiteral(Vec<u8>),
     Eof,
     Bad,
     Return,
+    If,
+    Else,
+    While,
+    Do,
+    For,
+    Break,
+    Continue,
+    Goto,
+    Switch,
+    Case,
+    Default,
+    Sizeof,
+    Struct,
+    Union,
+    Enum,
+    Typedef,
+    Short,
+    Long,
+    Signed,
+    Unsigned,
     Identifier(String),
     VarType(VartypeKind),
 }
@@ -35,34 +110,109 @@ pub enum TokenKind {
 impl Display for VartypeKind{
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            VartypeKind::Char => write!(f,"char"),
+            VartypeKind::SignedChar => write!(f,"signed char"),
+            VartypeKind::UnsignedChar => write!(f,"unsigned char"),
+            VartypeKind::Short => write!(f,"short"),
+            VartypeKind::UnsignedShort => write!(f,"unsigned short"),
             VartypeKind::Int => write!(f,"int"),
+            VartypeKind::UnsignedInt => write!(f,"unsigned int"),
+            VartypeKind::Long => write!(f,"long"),
+            VartypeKind::UnsignedLong => write!(f,"unsigned long"),
+            VartypeKind::LongLong => write!(f,"long long"),
+            VartypeKind::UnsignedLongLong => write!(f,"unsigned long long"),
             VartypeKind::Float => write!(f,"float"),
             VartypeKind::Double => write!(f,"double"),
         }
     }
 }
 
+impl TokenKind {
+    /// Whitespace and comments, which only separate the other tokens.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::WhiteSpace | TokenKind::Comment)
+    }
+}
+
 impl Display for TokenKind{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenKind::Number(num) => write!(f,"Number({})",num),
+            TokenKind::FloatNumber(num) => write!(f,"FloatNumber({:?})",num),
             TokenKind::Plus => write!(f,"Plus"),
+            TokenKind::PlusPlus => write!(f,"PlusPlus"),
+            TokenKind::PlusEqual => write!(f,"PlusEqual"),
             TokenKind::Minus => write!(f,"Minus"),
+            TokenKind::MinusMinus => write!(f,"MinusMinus"),
+            TokenKind::MinusEqual => write!(f,"MinusEqual"),
             TokenKind::Asterisk => write!(f,"Asterisk"),
+            TokenKind::AsteriskEqual => write!(f,"AsteriskEqual"),
             TokenKind::Slash => write!(f,"Slash"),
+            TokenKind::SlashEqual => write!(f,"SlashEqual"),
+            TokenKind::Percent => write!(f,"Percent"),
+            TokenKind::PercentEqual => write!(f,"PercentEqual"),
+            TokenKind::Bang => write!(f,"Bang"),
+            TokenKind::BangEqual => write!(f,"BangEqual"),
+            TokenKind::Tilde => write!(f,"Tilde"),
+            TokenKind::Ampersand => write!(f,"Ampersand"),
+            TokenKind::AmpersandAmpersand => write!(f,"AmpersandAmpersand"),
+            TokenKind::AmpersandEqual => write!(f,"AmpersandEqual"),
+            TokenKind::Pipe => write!(f,"Pipe"),
+            TokenKind::PipePipe => write!(f,"PipePipe"),
+            TokenKind::PipeEqual => write!(f,"PipeEqual"),
+            TokenKind::Caret => write!(f,"Caret"),
+            TokenKind::CaretEqual => write!(f,"CaretEqual"),
+            TokenKind::Less => write!(f,"Less"),
+            TokenKind::LessEqual => write!(f,"LessEqual"),
+            TokenKind::LessLess => write!(f,"LessLess"),
+            TokenKind::LessLessEqual => write!(f,"LessLessEqual"),
+            TokenKind::Greater => write!(f,"Greater"),
+            TokenKind::GreaterEqual => write!(f,"GreaterEqual"),
+            TokenKind::GreaterGreater => write!(f,"GreaterGreater"),
+            TokenKind::GreaterGreaterEqual => write!(f,"GreaterGreaterEqual"),
             TokenKind::LeftParen => write!(f,"LeftParen"),
             TokenKind::RightParen => write!(f,"RightParen"),
             TokenKind::SemiColon => write!(f,"SemiColon"),
+            TokenKind::Colon => write!(f,"Colon"),
+            TokenKind::Question => write!(f,"Question"),
+            TokenKind::Dot => write!(f,"Dot"),
+            TokenKind::Arrow => write!(f,"Arrow"),
             TokenKind::WhiteSpace => write!(f,"WhiteSpace"),
+            TokenKind::Comment => write!(f,"Comment"),
             TokenKind::Eof => write!(f,"Eof"),
             TokenKind::Bad => write!(f,"Bad"),
             TokenKind::Equal => write!(f,"Equal"),
+            TokenKind::EqualEqual => write!(f,"EqualEqual"),
             TokenKind::Comma => write!(f,"Comma"),
+            TokenKind::Hash => write!(f,"Hash"),
+            TokenKind::HashHash => write!(f,"HashHash"),
+            TokenKind::CharLiteral(value) => write!(f,"CharLiteral:{}",value),
+            TokenKind::StringLiteral(value) => write!(f,"StringLiteral:{}",value.escape_ascii()),
             TokenKind::LeftBracket => write!(f,"LeftBracket"),
             TokenKind::RightBracket => write!(f,"RightBracket"),
             TokenKind::LeftBrace => write!(f,"LeftBrace"),
             TokenKind::RightBrace => write!(f,"RightBrace"),
             TokenKind::Return => write!(f,"Return"),
+            TokenKind::If => write!(f,"If"),
+            TokenKind::Else => write!(f,"Else"),
+            TokenKind::While => write!(f,"While"),
+            TokenKind::Do => write!(f,"Do"),
+            TokenKind::For => write!(f,"For"),
+            TokenKind::Break => write!(f,"Break"),
+            TokenKind::Continue => write!(f,"Continue"),
+            TokenKind::Goto => write!(f,"Goto"),
+            TokenKind::Switch => write!(f,"Switch"),
+            TokenKind::Case => write!(f,"Case"),
+            TokenKind::Default => write!(f,"Default"),
+            TokenKind::Sizeof => write!(f,"Sizeof"),
+            TokenKind::Struct => write!(f,"Struct"),
+            TokenKind::Union => write!(f,"Union"),
+            TokenKind::Enum => write!(f,"Enum"),
+            TokenKind::Typedef => write!(f,"Typedef"),
+            TokenKind::Short => write!(f,"Short"),
+            TokenKind::Long => write!(f,"Long"),
+            TokenKind::Signed => write!(f,"Signed"),
+            TokenKind::Unsigned => write!(f,"Unsigned"),
             TokenKind::VarType(vartype) =>  write!(f, "{}", vartype),
             TokenKind::Identifier(name) => write!(f,"Identifier:{}",name),
         }
@@ -70,9 +220,11 @@ impl Display for TokenKind{
 }
 #[derive(Debug,PartialEq,Clone)]
 pub struct TextSpan {
-    pub(crate) start: usize,
-    end: usize,
-    literal: String,
+    pub start: usize,
+    pub end: usize,
+    pub literal: String,
+    /// Index of the file in the [`SourceMap`](crate::text::SourceMap) the span points into.
+    pub file: usize,
 }
 
 impl TextSpan {
@@ -81,8 +233,13 @@ impl TextSpan {
             start,
             end,
             literal,
+            file: 0,
         }
     }
+    pub fn in_file(mut self, file: usize) -> Self {
+        self.file = file;
+        self
+    }
     pub fn length(&self) -> usize {
         self.end - self.start
     }
@@ -90,8 +247,8 @@ impl TextSpan {
 
 #[derive(Debug,Clone,PartialEq)]
 pub struct Token {
-    pub(crate) kind: TokenKind,
-    pub(crate) span: TextSpan,
+    pub kind: TokenKind,
+    pub span: TextSpan,
 }
 
 impl Token {
@@ -109,24 +266,58 @@ macro_rules! hashmap {
 
 pub struct Lexer<'a> {
     input: &'a str,
+    file: usize,
+    /// Offset in bytes of the next character.
     current_pos: usize,
-    keywords: HashMap<String,TokenKind>
+    keywords: HashMap<String,TokenKind>,
+    diagnostics_bag: DiagnosticsBagCell,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::for_file(input, 0)
+    }
+    pub fn for_file(input: &'a str, file: usize) -> Self {
         let keywords = hashmap![
+        "char" => TokenKind::VarType(VartypeKind::Char),
         "int" => TokenKind::VarType(VartypeKind::Int),
         "float" => TokenKind::VarType(VartypeKind::Float),
         "double" => TokenKind::VarType(VartypeKind::Double),
         "return" => TokenKind::Return,
+        "if" => TokenKind::If,
+        "else" => TokenKind::Else,
+        "while" => TokenKind::While,
+        "do" => TokenKind::Do,
+        "for" => TokenKind::For,
+        "break" => TokenKind::Break,
+        "continue" => TokenKind::Continue,
+        "goto" => TokenKind::Goto,
+        "switch" => TokenKind::Switch,
+        "case" => TokenKind::Case,
+        "default" => TokenKind::Default,
+        "sizeof" => TokenKind::Sizeof,
+        "struct" => TokenKind::Struct,
+        "union" => TokenKind::Union,
+        "enum" => TokenKind::Enum,
+        "typedef" => TokenKind::Typedef,
+        "short" => TokenKind::Short,
+        "long" => TokenKind::Long,
+        "signed" => TokenKind::Signed,
+        "unsigned" => TokenKind::Unsigned,
     ];
         Self {
             input,
+            file,
             current_pos: 0,
             keywords,
+            diagnostics_bag: Rc::new(RefCell::new(DiagnosticBag::new())),
         }
     }
+    /// Reports malformed literals to `diagnostics_bag` rather than dropping them.
+    pub fn reporting_to(mut self, diagnostics_bag: DiagnosticsBagCell) -> Self {
+        self.diagnostics_bag = diagnostics_bag;
+        self
+    }
     fn get_token_kind(&self,input: &String) -> TokenKind {
         if let Some(token_kind) = self.keywords.get(input) {
             token_kind.clone()
@@ -137,32 +328,39 @@ impl<'a> Lexer<'a> {
     pub fn next_token(&mut self) -> Option<Token> {
         if self.current_pos == self.input.len() {
             let eof_char: char = '\0';
+            let end = self.current_pos;
             self.current_pos += 1;
             return Some(Token::new(
                 TokenKind::Eof,
-                TextSpan::new(0, 0, eof_char.to_string()),
+                TextSpan::new(end, end, eof_char.to_string()).in_file(self.file),
             ));
         }
         let c = self.current_char();
         c.map(|c|{
             let start = self.current_pos;
-            let mut kind = TokenKind::Bad;
-            if Self::is_number_start(&c) {
-                let number = self.consumer_number();
-                kind = TokenKind::Number(number);
+            let kind = if Self::is_number_start(&c) || (c == '.' && self.peek_char(1).is_some_and(|c| c.is_ascii_digit())) {
+                self.consumer_number()
             } else if Self::is_whitespace(&c){
                 self.consumer_whitespace();
-                kind= TokenKind::WhiteSpace;
+                TokenKind::WhiteSpace
+            } else if c == '/' && matches!(self.peek_char(1), Some('/' | '*')) {
+                self.consumer_comment();
+                TokenKind::Comment
             } else if Self::is_character_start(&c){
                let literal = self.consumer_literals();
-               kind = self.get_token_kind(&literal);
+               self.get_token_kind(&literal)
+            } else if c == '"' {
+                let (value,_) = self.consumer_quoted('"');
+                TokenKind::StringLiteral(value)
+            } else if c == '\'' {
+                self.consumer_character()
             }
             else {
-                kind = self.consumer_punctuation();
-            }
+                self.consumer_punctuation()
+            };
             let end = self.current_pos;
             let literal = self.input[start..end].to_string();
-            let span = TextSpan::new(start, end, literal);
+            let span = TextSpan::new(start, end, literal).in_file(self.file);
             Token::new(kind, span)
         })
 
@@ -173,35 +371,64 @@ impl<'a> Lexer<'a> {
             return None;
         }
         let c = self.current_char();
-        self.current_pos += 1;
+        self.current_pos += c.map_or(1, char::len_utf8);
         c
     }
     fn consumer_punctuation(&mut self) -> TokenKind{
         let c = self.consume().unwrap();
         match c {
-            '+' => TokenKind::Plus,
-            '-' => TokenKind::Minus,
-            '*' => TokenKind::Asterisk,
-            '/' => TokenKind::Slash,
+            '+' => self.consume_followed_by(&[('+',TokenKind::PlusPlus),('=',TokenKind::PlusEqual)],TokenKind::Plus),
+            '-' => self.consume_followed_by(&[('-',TokenKind::MinusMinus),('=',TokenKind::MinusEqual),('>',TokenKind::Arrow)],TokenKind::Minus),
+            '*' => self.consume_followed_by(&[('=',TokenKind::AsteriskEqual)],TokenKind::Asterisk),
+            '/' => self.consume_followed_by(&[('=',TokenKind::SlashEqual)],TokenKind::Slash),
+            '%' => self.consume_followed_by(&[('=',TokenKind::PercentEqual)],TokenKind::Percent),
+            '~' => TokenKind::Tilde,
+            '^' => self.consume_followed_by(&[('=',TokenKind::CaretEqual)],TokenKind::Caret),
+            '!' => self.consume_followed_by(&[('=',TokenKind::BangEqual)],TokenKind::Bang),
+            '=' => self.consume_followed_by(&[('=',TokenKind::EqualEqual)],TokenKind::Equal),
+            '&' => self.consume_followed_by(&[('&',TokenKind::AmpersandAmpersand),('=',TokenKind::AmpersandEqual)],TokenKind::Ampersand),
+            '|' => self.consume_followed_by(&[('|',TokenKind::PipePipe),('=',TokenKind::PipeEqual)],TokenKind::Pipe),
+            '<' => match self.consume_followed_by(&[('=',TokenKind::LessEqual),('<',TokenKind::LessLess)],TokenKind::Less) {
+                TokenKind::LessLess => self.consume_followed_by(&[('=',TokenKind::LessLessEqual)],TokenKind::LessLess),
+                kind => kind,
+            },
+            '>' => match self.consume_followed_by(&[('=',TokenKind::GreaterEqual),('>',TokenKind::GreaterGreater)],TokenKind::Greater) {
+                TokenKind::GreaterGreater => self.consume_followed_by(&[('=',TokenKind::GreaterGreaterEqual)],TokenKind::GreaterGreater),
+                kind => kind,
+            },
             '(' => TokenKind::LeftParen,
             ')' => TokenKind::RightParen,
-            '=' => TokenKind::Equal,
             ';' => TokenKind::SemiColon,
+            ':' => TokenKind::Colon,
+            '?' => TokenKind::Question,
+            '.' => TokenKind::Dot,
             ',' => TokenKind::Comma,
             '[' => TokenKind::LeftBracket,
             ']' => TokenKind::RightBracket,
             '{' => TokenKind::LeftBrace,
             '}' => TokenKind::RightBrace,
+            '#' => self.consume_followed_by(&[('#',TokenKind::HashHash)],TokenKind::Hash),
             _   => TokenKind::Bad,
         }
     }
+    /// Picks the longer token whose next character follows the one just consumed, or `single`.
+    fn consume_followed_by(&mut self,followers:&[(char,TokenKind)],single:TokenKind) -> TokenKind{
+        let next = self.current_char();
+        match followers.iter().find(|(c,_)| Some(*c) == next) {
+            Some((_,kind)) => {
+                self.consume();
+                kind.clone()
+            }
+            None => single,
+        }
+    }
     
     fn consumer_number_radix_10(&mut self) -> i64{
         let mut number: i64 = 0;
         while let Some(c) = self.current_char() {
-            if c.is_digit(10) {
+            if c.is_ascii_digit() {
                 self.consume();
-                number = number * 10 + c.to_digit(10).unwrap() as i64;
+                number = number.wrapping_mul(10).wrapping_add(c.to_digit(10).unwrap() as i64);
             } else {
                 break;
             }
@@ -214,7 +441,7 @@ impl<'a> Lexer<'a> {
         while let Some(c) = self.current_char() {
             if c.is_digit(8) {
                 self.consume();
-                number = number * 8 + c.to_digit(8).unwrap() as i64;
+                number = number.wrapping_mul(8).wrapping_add(c.to_digit(8).unwrap() as i64);
             } else {
                 break;
             }
@@ -225,9 +452,9 @@ impl<'a> Lexer<'a> {
     fn consumer_number_radix_16(&mut self) -> i64{
         let mut number: i64 = 0;
         while let Some(c) = self.current_char() {
-            if c.is_digit(16) {
+            if c.is_ascii_hexdigit() {
                 self.consume();
-                number = number * 16 + c.to_digit(16).unwrap() as i64;
+                number = number.wrapping_mul(16).wrapping_add(c.to_digit(16).unwrap() as i64);
             } else {
                 break;
             }
@@ -235,10 +462,70 @@ impl<'a> Lexer<'a> {
         number
     }
 
-    fn consumer_number(&mut self) -> i64 {
+    /// Reads a numeric constant with its suffix, as `0x1Fu` or `1.5e-3f`. The whole of it stays
+    /// in the literal of the token for the parser to check; integer values too large for 64 bits
+    /// wrap around, and malformed floating constants get a NaN value.
+    fn consumer_number(&mut self) -> TokenKind {
+        let start = self.current_pos;
+        let number = if self.current_char() == Some('.') { 0 } else { self.consumer_number_digits() };
+        let mut previous = None;
+        while let Some(c) = self.current_char() {
+            // A sign right after an exponent letter belongs to the number, as in `1e-3`.
+            let is_sign = matches!(c, '+' | '-') && matches!(previous, Some('e' | 'E' | 'p' | 'P'));
+            if Self::is_literal(&c) || c == '.' || is_sign {
+                self.consume();
+                previous = Some(c);
+            } else {
+                break;
+            }
+        }
+        let literal = &self.input[start..self.current_pos];
+        let hex = literal.get(..2).is_some_and(|prefix| prefix.eq_ignore_ascii_case("0x"));
+        let exponent = if hex { ['p','P'] } else { ['e','E'] };
+        if literal.contains('.') || literal.contains(exponent) {
+            TokenKind::FloatNumber(Self::floating_value(literal))
+        } else {
+            TokenKind::Number(number)
+        }
+    }
+    /// The value of a floating constant, ignoring its suffix.
+    fn floating_value(literal:&str) -> f64 {
+        let digits = literal.strip_suffix(['f','F','l','L']).unwrap_or(literal);
+        match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            Some(hex) => Self::hex_floating_value(hex).unwrap_or(f64::NAN),
+            // Rust takes a few spellings C does not, as `inf`, but none of them start with a digit or a dot.
+            None => digits.parse().unwrap_or(f64::NAN),
+        }
+    }
+    /// The value of a hexadecimal floating constant after its `0x`, as `1.8p3`.
+    fn hex_floating_value(digits:&str) -> Option<f64> {
+        let (mantissa,exponent) = digits.split_once(['p','P'])?;
+        let mut exponent: i32 = exponent.parse().ok()?;
+        let (whole,fraction) = mantissa.split_once('.').unwrap_or((mantissa,""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let mut value: u128 = 0;
+        for (index,c) in whole.chars().chain(fraction.chars()).enumerate() {
+            let digit = c.to_digit(16)? as u128;
+            // Digits beyond what 128 bits hold are too small to change the rounded value.
+            if value >> 120 == 0 {
+                value = value * 16 + digit;
+                if index >= whole.len() {
+                    exponent = exponent.saturating_sub(4);
+                }
+            } else if index < whole.len() {
+                exponent = exponent.saturating_add(4);
+            }
+        }
+        // Scaling in two steps keeps the intermediate power of two representable.
+        let half = exponent / 2;
+        Some(value as f64 * 2f64.powi(half) * 2f64.powi(exponent - half))
+    }
+    fn consumer_number_digits(&mut self) -> i64 {
         if self.current_char().unwrap() == '0'{
             self.consume();
-            if self.current_char().unwrap() == 'x'{
+            if matches!(self.current_char(), Some('x' | 'X')){
                 self.consume();
                 return self.consumer_number_radix_16();
             }
@@ -255,6 +542,40 @@ impl<'a> Lexer<'a> {
             }
         }
     }
+    /// Reads a `//` comment up to the end of the line, which a backslash ending it continues,
+    /// or a `/* */` comment, which may span lines.
+    fn consumer_comment(&mut self){
+        let start = self.current_pos;
+        self.consume();
+        if self.consume() == Some('/') {
+            let mut previous = None;
+            while let Some(c) = self.current_char() {
+                if c == '\n' && previous != Some('\\') {
+                    break;
+                }
+                // A `\r` of a `\r\n` line end does not come between a backslash and its newline.
+                if c != '\r' {
+                    previous = Some(c);
+                }
+                self.consume();
+            }
+            return;
+        }
+        loop {
+            match self.consume() {
+                Some('*') if self.current_char() == Some('/') => {
+                    self.consume();
+                    return;
+                }
+                Some(_) => {}
+                None => {
+                    let span = TextSpan::new(start,start + 2,"/*".to_string()).in_file(self.file);
+                    self.diagnostics_bag.borrow_mut().report_error("Unterminated /* comment".to_string(),span);
+                    return;
+                }
+            }
+        }
+    }
     fn consumer_literals(&mut self) -> String{
         let mut literal = String::new();
         while let Some(c) = self.current_char() {
@@ -267,16 +588,139 @@ impl<'a> Lexer<'a> {
         }
         literal
     }
+    /// Reads a character constant. One byte gives a `char` value sign-extended to `int`;
+    /// several are packed into the `int` from the first, most significant, one on.
+    fn consumer_character(&mut self) -> TokenKind{
+        let start = self.current_pos;
+        let (bytes,terminated) = self.consumer_quoted('\'');
+        let value = match bytes.as_slice() {
+            _ if !terminated => 0,
+            [byte] => *byte as i8 as i64,
+            [] => {
+                self.report_error("Empty character constant".to_string(),start);
+                0
+            }
+            _ => {
+                let span = self.span_from(start);
+                self.diagnostics_bag.borrow_mut().report_warning("Multi-character character constant".to_string(),span);
+                bytes.iter().fold(0i32, |value,byte| value << 8 | *byte as i32) as i64
+            }
+        };
+        TokenKind::CharLiteral(value)
+    }
+    /// Reads a character constant or a string literal up to the closing `quote`, returning the
+    /// bytes it stands for in UTF-8 and whether it was closed before the end of the line.
+    fn consumer_quoted(&mut self,quote:char) -> (Vec<u8>,bool){
+        let start = self.current_pos;
+        self.consume();
+        let mut bytes = Vec::new();
+        loop {
+            match self.current_char() {
+                Some(c) if c == quote => {
+                    self.consume();
+                    return (bytes,true);
+                }
+                None | Some('\n') => {
+                    self.report_error(format!("Missing terminating {} character",quote),start);
+                    return (bytes,false);
+                }
+                Some('\\') => self.consumer_escape(&mut bytes),
+                Some(c) => {
+                    self.consume();
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0;4]).as_bytes());
+                }
+            }
+        }
+    }
+    /// Reads an escape sequence, appending the bytes it stands for.
+    fn consumer_escape(&mut self,bytes:&mut Vec<u8>){
+        let start = self.current_pos;
+        self.consume();
+        let Some(c) = self.current_char() else {
+            return;
+        };
+        self.consume();
+        let byte = match c {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'v' => 0x0b,
+            '\\' | '\'' | '"' | '?' => c as u8,
+            // A backslash ending the line joins the next one to the literal.
+            '\n' => return,
+            '0'..='7' => {
+                let value = self.consumer_escape_digits(8,c.to_digit(8).unwrap(),2);
+                if value > 0xff {
+                    self.report_error("Octal escape sequence out of range".to_string(),start);
+                }
+                value as u8
+            }
+            'x' => {
+                let end = self.current_pos;
+                let value = self.consumer_escape_digits(16,0,usize::MAX);
+                if self.current_pos == end {
+                    self.report_error("\\x used with no following hex digits".to_string(),start);
+                } else if value > 0xff {
+                    self.report_error("Hex escape sequence out of range".to_string(),start);
+                }
+                value as u8
+            }
+            'u' | 'U' => {
+                let length = if c == 'u' { 4 } else { 8 };
+                let end = self.current_pos;
+                let value = self.consumer_escape_digits(16,0,length);
+                match char::from_u32(value) {
+                    _ if self.current_pos - end < length => self.report_error("Incomplete universal character name".to_string(),start),
+                    Some(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0;4]).as_bytes()),
+                    None => self.report_error(format!("Invalid universal character: {}",&self.input[start..self.current_pos]),start),
+                }
+                return;
+            }
+            _ => {
+                // The character stands for itself, so the literal is not also reported as empty.
+                self.report_error(format!("Unknown escape sequence: \\{}",c),start);
+                bytes.extend_from_slice(c.encode_utf8(&mut [0;4]).as_bytes());
+                return;
+            }
+        };
+        bytes.push(byte);
+    }
+    /// Reads up to `count` more digits of an escape sequence in `radix` after `value`.
+    /// Values too large for 32 bits saturate.
+    fn consumer_escape_digits(&mut self,radix:u32,mut value:u32,count:usize) -> u32{
+        for _ in 0..count {
+            let Some(digit) = self.current_char().and_then(|c| c.to_digit(radix)) else {
+                break;
+            };
+            self.consume();
+            value = value.saturating_mul(radix).saturating_add(digit);
+        }
+        value
+    }
+    fn span_from(&self,start:usize) -> TextSpan{
+        TextSpan::new(start,self.current_pos,self.input[start..self.current_pos].to_string()).in_file(self.file)
+    }
+    /// Reports an error on the text from `start` to the current position.
+    fn report_error(&self,message:String,start:usize){
+        let span = self.span_from(start);
+        self.diagnostics_bag.borrow_mut().report_error(message,span);
+    }
     fn is_number_start(c: &char) -> bool {
-        c.is_digit(10)
+        c.is_ascii_digit()
     }
 
-    fn is_character_start(c: &char) -> bool { c.is_alphabetic() }
+    fn is_character_start(c: &char) -> bool { c.is_alphabetic() || *c == '_' }
 
-    fn is_literal(c: &char) -> bool { c.is_alphanumeric() }
+    fn is_literal(c: &char) -> bool { c.is_alphanumeric() || *c == '_' }
 
     fn is_whitespace(c: &char) -> bool { c.is_whitespace() }
     fn current_char(&self) -> Option<char> {
-        self.input.chars().nth(self.current_pos)
+        self.peek_char(0)
+    }
+    fn peek_char(&self,offset:usize) -> Option<char> {
+        self.input.get(self.current_pos..)?.chars().nth(offset)
     }
 }
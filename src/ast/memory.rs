@@ -0,0 +1,101 @@
+/// Byte-addressed storage for the objects of an interpreted program.
+///
+/// Objects are allocated like a stack: globals first, then the locals of each call and
+/// block, released again with [`Memory::release`] when their scope ends. Address 0 is
+/// never handed out, so it can serve as the null pointer. String literals live apart,
+/// in a read-only static segment that is never released.
+#[derive(Debug,Clone)]
+pub struct Memory{
+    bytes:Vec<u8>,
+    statics:Vec<u8>,
+}
+
+/// The address of the first byte of the static segment.
+const STATIC_BASE: usize = 1 << 40;
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory{
+    /// Addresses below this one are reserved, so that no object lives at the null pointer.
+    pub const RESERVED: usize = 8;
+
+    pub fn new() -> Self{
+        Self{bytes:vec![0;Self::RESERVED],statics:Vec::new()}
+    }
+    /// Places a copy of `bytes` in the static segment, returning its address.
+    pub fn allocate_static(&mut self,bytes:&[u8]) -> usize{
+        let address = STATIC_BASE + self.statics.len();
+        self.statics.extend_from_slice(bytes);
+        address
+    }
+    /// Reserves `size` zeroed bytes aligned to `align`, returning their address.
+    pub fn allocate(&mut self,size:usize,align:usize) -> usize{
+        let align = align.max(1);
+        let address = self.bytes.len().div_ceil(align) * align;
+        self.bytes.resize(address + size,0);
+        address
+    }
+    /// The address the next allocation starts from, to be handed back to [`Memory::release`].
+    pub fn mark(&self) -> usize{
+        self.bytes.len()
+    }
+    /// Frees everything allocated since `mark` was taken.
+    pub fn release(&mut self,mark:usize){
+        self.bytes.truncate(mark.max(Self::RESERVED));
+    }
+    /// Reads a little-endian integer of `size` bytes, sign-extended to 64 bits, `None` when
+    /// the bytes are not all allocated.
+    pub fn load(&self,address:usize,size:usize) -> Option<i64>{
+        let bytes = self.object(address,size)?;
+        let mut value = [0u8;8];
+        value[..size].copy_from_slice(bytes);
+        let shift = 64 - 8 * size as u32;
+        Some((i64::from_le_bytes(value) << shift) >> shift)
+    }
+    /// Writes the low `size` bytes of `value` in little-endian order, `None` when the bytes
+    /// are not all allocated or lie in the read-only static segment.
+    pub fn store(&mut self,address:usize,size:usize,value:i64) -> Option<()>{
+        let bytes = value.to_le_bytes();
+        self.object_mut(address,size)?.copy_from_slice(&bytes[..size]);
+        Some(())
+    }
+    /// Copies `size` bytes from `source` to `destination`, as struct assignment does.
+    pub fn copy(&mut self,destination:usize,source:usize,size:usize) -> Option<()>{
+        let bytes = self.read(source,size)?;
+        self.write(destination,&bytes)
+    }
+    /// The raw bytes of the `size`-byte object at `address`.
+    pub fn read(&self,address:usize,size:usize) -> Option<Vec<u8>>{
+        self.range(address,size).map(<[u8]>::to_vec)
+    }
+    /// Overwrites memory at `address` with `bytes`.
+    pub fn write(&mut self,address:usize,bytes:&[u8]) -> Option<()>{
+        self.range_mut(address,bytes.len())?.copy_from_slice(bytes);
+        Some(())
+    }
+    fn object(&self,address:usize,size:usize) -> Option<&[u8]>{
+        self.range(address,size).filter(|_| size <= 8)
+    }
+    fn object_mut(&mut self,address:usize,size:usize) -> Option<&mut [u8]>{
+        self.range_mut(address,size).filter(|_| size <= 8)
+    }
+    fn range(&self,address:usize,size:usize) -> Option<&[u8]>{
+        if address < Self::RESERVED {
+            return None;
+        }
+        match address.checked_sub(STATIC_BASE) {
+            Some(offset) => self.statics.get(offset..offset.checked_add(size)?),
+            None => self.bytes.get(address..address.checked_add(size)?),
+        }
+    }
+    fn range_mut(&mut self,address:usize,size:usize) -> Option<&mut [u8]>{
+        if !(Self::RESERVED..STATIC_BASE).contains(&address) {
+            return None;
+        }
+        self.bytes.get_mut(address..address.checked_add(size)?)
+    }
+}
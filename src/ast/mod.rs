@@ -1,5 +1,6 @@
-use std::env::var;
-use std::fmt::Display;
+use std::cell::RefCell;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
 use crate::ast::expression::*;
 use crate::ast::statement::*;
 use crate::ast::visitor::*;
@@ -7,18 +8,28 @@ use crate::ast::printer::*;
 use crate::ast::progranunit::ASTProgramunit;
 
 pub mod lexer;
+pub mod preprocessor;
 pub mod parser;
 pub mod evaluator;
 pub mod statement;
 pub mod visitor;
 pub mod printer;
-mod expression;
-mod progranunit;
+pub mod scope;
+pub mod memory;
+pub mod expression;
+pub mod progranunit;
+pub mod value;
 
 pub struct Ast{
     pub program_units: Vec<ASTProgramunit>
 }
 
+impl Default for Ast {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Ast {
     pub fn new() -> Self{
         Self{program_units: Vec::new()}
@@ -31,12 +42,13 @@ impl Ast {
             visitor.visit_program_unit(program_unit);
         }
     }
-    pub fn visualize(&self){
-        let mut printer = ASTPrinter{indent:0};
+    pub fn visualize(&self) -> String{
+        let mut printer = ASTPrinter::new();
         self.visit(&mut printer);
+        printer.output
     }
 }
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub struct GrammarFunctiontype{
     pub arguments:Vec<GrammarVartype>,
     pub returntype:GrammarVartype
@@ -49,42 +61,357 @@ impl GrammarFunctiontype{
 
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum GrammarVartype{
     Direct(lexer::VartypeKind),
     Ref(Box<GrammarVartype>),
+    /// An array of a fixed number of elements.
+    Array(Box<GrammarVartype>,usize),
+    Record(GrammarRecordtype),
     Function(Box<GrammarFunctiontype>)
 }
 impl GrammarVartype{
     pub fn new( vartype:&lexer::VartypeKind) -> Self{
         GrammarVartype::Direct(vartype.clone())
     }
-    pub fn Ref(vartype:GrammarVartype) -> Self{
+    pub fn reference(vartype:GrammarVartype) -> Self{
         GrammarVartype::Ref(Box::new(vartype))
     }
-    pub fn Function(arguments:Vec<GrammarVartype>,returntype:GrammarVartype) -> Self {
+    pub fn array(element:GrammarVartype,length:usize) -> Self{
+        GrammarVartype::Array(Box::new(element),length)
+    }
+    pub fn function(arguments:Vec<GrammarVartype>,returntype:GrammarVartype) -> Self {
         GrammarVartype::Function(Box::new(GrammarFunctiontype::new(arguments,returntype)))
     }
+    pub fn int() -> Self{
+        GrammarVartype::Direct(lexer::VartypeKind::Int)
+    }
+    pub fn is_integer(&self) -> bool{
+        matches!(self, GrammarVartype::Direct(kind) if !matches!(kind, lexer::VartypeKind::Float | lexer::VartypeKind::Double))
+    }
+    pub fn is_floating(&self) -> bool{
+        matches!(self, GrammarVartype::Direct(lexer::VartypeKind::Float | lexer::VartypeKind::Double))
+    }
+    pub fn is_arithmetic(&self) -> bool{
+        self.is_integer() || self.is_floating()
+    }
+    /// Whether values of this type compare with 0, as conditions do: arithmetic values and
+    /// pointers, with arrays and functions as the pointers they decay to.
+    pub fn is_scalar(&self) -> bool{
+        self.is_arithmetic() || self.decay().is_pointer()
+    }
+    pub fn is_unsigned(&self) -> bool{
+        matches!(self, GrammarVartype::Direct(
+            lexer::VartypeKind::UnsignedChar | lexer::VartypeKind::UnsignedShort | lexer::VartypeKind::UnsignedInt |
+            lexer::VartypeKind::UnsignedLong | lexer::VartypeKind::UnsignedLongLong
+        ))
+    }
+    /// The integer conversion rank, higher for wider integer types and 0 for other types.
+    fn rank(&self) -> u8{
+        match self {
+            GrammarVartype::Direct(kind) => match kind {
+                lexer::VartypeKind::Char | lexer::VartypeKind::SignedChar | lexer::VartypeKind::UnsignedChar => 1,
+                lexer::VartypeKind::Short | lexer::VartypeKind::UnsignedShort => 2,
+                lexer::VartypeKind::Int | lexer::VartypeKind::UnsignedInt => 3,
+                lexer::VartypeKind::Long | lexer::VartypeKind::UnsignedLong => 4,
+                lexer::VartypeKind::LongLong | lexer::VartypeKind::UnsignedLongLong => 5,
+                lexer::VartypeKind::Float | lexer::VartypeKind::Double => 0,
+            },
+            _ => 0,
+        }
+    }
+    /// The unsigned type of the same width as this integer type.
+    fn to_unsigned(&self) -> GrammarVartype{
+        let kind = match self {
+            GrammarVartype::Direct(lexer::VartypeKind::Char | lexer::VartypeKind::SignedChar) => lexer::VartypeKind::UnsignedChar,
+            GrammarVartype::Direct(lexer::VartypeKind::Short) => lexer::VartypeKind::UnsignedShort,
+            GrammarVartype::Direct(lexer::VartypeKind::Int) => lexer::VartypeKind::UnsignedInt,
+            GrammarVartype::Direct(lexer::VartypeKind::Long) => lexer::VartypeKind::UnsignedLong,
+            GrammarVartype::Direct(lexer::VartypeKind::LongLong) => lexer::VartypeKind::UnsignedLongLong,
+            _ => return self.clone(),
+        };
+        GrammarVartype::Direct(kind)
+    }
+    /// The integer promotion: operands narrower than `int` are used as `int`.
+    pub fn promote(&self) -> GrammarVartype{
+        let ty = self.decay();
+        if ty.is_integer() && ty.rank() < GrammarVartype::int().rank() {
+            return GrammarVartype::int();
+        }
+        ty
+    }
+    /// The type the operands of an arithmetic operator are converted to, following the
+    /// usual arithmetic conversions.
+    pub fn common(&self,other:&GrammarVartype) -> GrammarVartype{
+        let (left,right) = (self.promote(),other.promote());
+        for floating in [lexer::VartypeKind::Double,lexer::VartypeKind::Float] {
+            if left == GrammarVartype::Direct(floating.clone()) || right == GrammarVartype::Direct(floating.clone()) {
+                return GrammarVartype::Direct(floating);
+            }
+        }
+        if !left.is_integer() || !right.is_integer() || left == right {
+            return left;
+        }
+        if left.is_unsigned() == right.is_unsigned() {
+            return if left.rank() >= right.rank() { left } else { right };
+        }
+        let (unsigned,signed) = if left.is_unsigned() { (left,right) } else { (right,left) };
+        if unsigned.rank() >= signed.rank() {
+            unsigned
+        } else if signed.size() > unsigned.size() {
+            signed
+        } else {
+            signed.to_unsigned()
+        }
+    }
+    pub fn is_pointer(&self) -> bool{
+        matches!(self, GrammarVartype::Ref(_))
+    }
+    pub fn is_array(&self) -> bool{
+        matches!(self, GrammarVartype::Array(..))
+    }
+    pub fn is_record(&self) -> bool{
+        matches!(self, GrammarVartype::Record(_))
+    }
+    /// Whether objects of this type are used through their address rather than loaded as a value.
+    pub fn is_aggregate(&self) -> bool{
+        self.is_array() || self.is_record()
+    }
+    /// Whether `source` may be stored in an object of this type, by assignment, initialization,
+    /// passing an argument or returning: arithmetic values convert to each other, a record
+    /// takes a record of the same type, and a pointer takes pointers or a null pointer constant.
+    pub fn is_assignable_from(&self,source:&ASTExpression) -> bool{
+        let from = source.ty.decay();
+        match self {
+            _ if self.is_arithmetic() => from.is_arithmetic(),
+            GrammarVartype::Record(_) => *self == from,
+            GrammarVartype::Ref(_) => from.is_pointer() || source.is_null_pointer_constant(),
+            _ => false,
+        }
+    }
+    /// The type a value of this type is converted to when used in an expression:
+    /// arrays become pointers to their first element and functions pointers to themselves.
+    pub fn decay(&self) -> GrammarVartype{
+        match self {
+            GrammarVartype::Array(element,_) => GrammarVartype::reference((**element).clone()),
+            GrammarVartype::Function(_) => GrammarVartype::reference(self.clone()),
+            _ => self.clone(),
+        }
+    }
+    /// The type a pointer points to, `None` for other types.
+    pub fn pointee(&self) -> Option<&GrammarVartype>{
+        match self {
+            GrammarVartype::Ref(pointee) => Some(pointee),
+            _ => None,
+        }
+    }
+    /// Size in bytes of an object of this type.
+    pub fn size(&self) -> usize{
+        match self {
+            GrammarVartype::Direct(kind) => match kind {
+                lexer::VartypeKind::Char | lexer::VartypeKind::SignedChar | lexer::VartypeKind::UnsignedChar => 1,
+                lexer::VartypeKind::Short | lexer::VartypeKind::UnsignedShort => 2,
+                lexer::VartypeKind::Int | lexer::VartypeKind::UnsignedInt => 4,
+                lexer::VartypeKind::Long | lexer::VartypeKind::UnsignedLong => 8,
+                lexer::VartypeKind::LongLong | lexer::VartypeKind::UnsignedLongLong => 8,
+                lexer::VartypeKind::Float => 4,
+                lexer::VartypeKind::Double => 8,
+            },
+            GrammarVartype::Ref(_) => 8,
+            GrammarVartype::Array(element,length) => element.size() * length,
+            GrammarVartype::Record(record) => record.0.borrow().size,
+            GrammarVartype::Function(_) => 1,
+        }
+    }
+    /// Converts an integer value to this type, as a cast or an assignment does: it wraps
+    /// around to the width of the type, zero-extended when unsigned and sign-extended otherwise.
+    /// 64-bit unsigned values keep their bits in the `i64`.
+    pub fn convert(&self,value:i64) -> i64{
+        if !self.is_integer() || self.size() == 8 {
+            return value;
+        }
+        let shift = 64 - 8 * self.size() as u32;
+        if self.is_unsigned() {
+            ((value as u64) << shift >> shift) as i64
+        } else {
+            (value << shift) >> shift
+        }
+    }
+    pub fn align(&self) -> usize{
+        match self {
+            GrammarVartype::Array(element,_) => element.align(),
+            GrammarVartype::Record(record) => record.0.borrow().align,
+            _ => self.size(),
+        }
+    }
+}
+
+impl GrammarVartype{
+    /// Spells the type as C would declare `declarator` with it, as `int (*)[3]` for an empty one.
+    fn declare(&self,declarator:String) -> String{
+        match self{
+            GrammarVartype::Direct(vartype) => format!("{} {}", vartype, declarator),
+            GrammarVartype::Record(record) => format!("{} {}", record, declarator),
+            GrammarVartype::Ref(pointee) => match pointee.as_ref() {
+                GrammarVartype::Array(..) | GrammarVartype::Function(_) => pointee.declare(format!("(*{})",declarator)),
+                _ => pointee.declare(format!("*{}",declarator)),
+            },
+            GrammarVartype::Array(element,length) => element.declare(format!("{}[{}]",declarator,length)),
+            GrammarVartype::Function(function) => {
+                let arguments: Vec<String> = function.arguments.iter().map(|argument| argument.to_string()).collect();
+                function.returntype.declare(format!("{}({})",declarator,arguments.join(", ")))
+            }
+        }
+    }
+}
+
+impl Display for GrammarVartype{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.declare(String::new()).trim_end())
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum GrammarRecordKind{
+    Struct,
+    Union,
+}
+
+impl Display for GrammarRecordKind{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarRecordKind::Struct => write!(f, "struct"),
+            GrammarRecordKind::Union => write!(f, "union"),
+        }
+    }
 }
 
 #[derive(Debug,Clone)]
-pub enum LeftValue {
-    Variable(String),
+pub struct GrammarMember{
+    pub name:String,
+    pub ty:GrammarVartype,
+    /// Offset in bytes from the start of the enclosing record.
+    pub offset:usize,
 }
 
-impl LeftValue {
-    pub fn variable(name:ASTExpression) -> Self{
-        match name.kind {
-            ASTExpressionKind::Variable(variable) => LeftValue::Variable(variable.name),
-            _ => unreachable!()
+#[derive(Debug)]
+pub struct GrammarRecord{
+    pub kind:GrammarRecordKind,
+    pub tag:Option<String>,
+    /// `None` until the definition with the member list has been seen.
+    pub members:Option<Vec<GrammarMember>>,
+    pub size:usize,
+    pub align:usize,
+}
+
+/// A `struct` or `union` type. Every use of a tag shares it, so a record can point to
+/// itself and be completed after it was first named; two record types are the same
+/// only when they come from the same declaration.
+#[derive(Clone)]
+pub struct GrammarRecordtype(Rc<RefCell<GrammarRecord>>);
+
+impl GrammarRecordtype{
+    /// An incomplete record, until [`GrammarRecordtype::complete`] gives it members.
+    pub fn new(kind:GrammarRecordKind,tag:Option<String>) -> Self{
+        GrammarRecordtype(Rc::new(RefCell::new(GrammarRecord{kind,tag,members:None,size:0,align:1})))
+    }
+    pub fn kind(&self) -> GrammarRecordKind{
+        self.0.borrow().kind
+    }
+    pub fn tag(&self) -> Option<String>{
+        self.0.borrow().tag.clone()
+    }
+    pub fn is_complete(&self) -> bool{
+        self.0.borrow().members.is_some()
+    }
+    /// Lays out the members with C alignment rules: each starts at the next multiple of its
+    /// alignment (all at 0 in a union), and the size is rounded up to the largest alignment.
+    /// Members given without a name are anonymous records whose own members are hoisted.
+    pub fn complete(&self,members:Vec<(Option<String>,GrammarVartype)>){
+        let kind = self.kind();
+        let mut layout = vec![];
+        let mut size: usize = 0;
+        let mut align: usize = 1;
+        for (name,ty) in members {
+            let offset = match kind {
+                GrammarRecordKind::Struct => size.div_ceil(ty.align()) * ty.align(),
+                GrammarRecordKind::Union => 0,
+            };
+            size = size.max(offset + ty.size());
+            align = align.max(ty.align());
+            match (name,&ty) {
+                (Some(name),_) => layout.push(GrammarMember{name,ty,offset}),
+                (None,GrammarVartype::Record(inner)) => {
+                    for member in inner.members() {
+                        layout.push(GrammarMember{offset:offset + member.offset,..member});
+                    }
+                }
+                (None,_) => {}
+            }
+        }
+        let mut record = self.0.borrow_mut();
+        record.members = Some(layout);
+        record.size = size.div_ceil(align) * align;
+        record.align = align;
+    }
+    pub fn members(&self) -> Vec<GrammarMember>{
+        self.0.borrow().members.clone().unwrap_or_default()
+    }
+    pub fn member(&self,name:&str) -> Option<GrammarMember>{
+        self.0.borrow().members.as_ref()?.iter().find(|member| member.name == name).cloned()
+    }
+}
+
+impl PartialEq for GrammarRecordtype{
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0,&other.0)
+    }
+}
+
+impl Display for GrammarRecordtype{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let record = self.0.borrow();
+        match &record.tag {
+            Some(tag) => write!(f, "{} {}", record.kind, tag),
+            None => write!(f, "{} <anonymous>", record.kind),
         }
     }
 }
 
-impl Display for LeftValue{
+/// Only names the record: its members may refer back to it.
+impl Debug for GrammarRecordtype{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self{
-            LeftValue::Variable(name) => write!(f, "{}", name),
+        write!(f, "Record({})", self)
+    }
+}
+
+/// An expression designating an object, which can be assigned to or have its address taken.
+#[derive(Debug,Clone)]
+pub enum LeftValue {
+    Variable(String),
+    /// `*expr`, the object the operand points to.
+    Deref(Box<ASTUnaryExpression>),
+    /// `expr.name` or `expr->name`.
+    Member(Box<ASTMemberExpression>),
+}
+
+impl LeftValue {
+    /// Whether `expr` designates an object.
+    pub fn is_left_value(expr:&ASTExpression) -> bool{
+        match &expr.kind {
+            ASTExpressionKind::Variable(_) | ASTExpressionKind::String(_) => true,
+            ASTExpressionKind::Unary(unary) => matches!(unary.operator.kind, ASTUnaryOperatorKind::Dereference),
+            ASTExpressionKind::Member(member) => member.arrow || LeftValue::is_left_value(&member.base),
+            _ => false,
+        }
+    }
+    /// Converts an expression the parser checked with [`LeftValue::is_left_value`].
+    pub fn new(expr:ASTExpression) -> Self{
+        match expr.kind {
+            ASTExpressionKind::Variable(variable) => LeftValue::Variable(variable.name),
+            ASTExpressionKind::Unary(unary) => LeftValue::Deref(Box::new(unary)),
+            ASTExpressionKind::Member(member) => LeftValue::Member(Box::new(member)),
+            _ => unreachable!()
         }
     }
 }
@@ -2,6 +2,8 @@ use std::cell::Cell;
 use crate::ast::*;
 use crate::ast::lexer::TokenKind;
 use crate::ast::progranunit::*;
+use crate::ast::scope::ScopeStack;
+use crate::ast::value::Value;
 use crate::diagnostics::*;
 use super::ASTStatement;
 use super::lexer::*;
@@ -9,6 +11,12 @@ pub struct Counter{
     count:Cell<usize>,
 }
 
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Counter {
     pub fn new() -> Self{
         Self{count:Cell::new(0)}
@@ -21,41 +29,126 @@ impl Counter {
     }
 }
 
+/// The `case` values and `default` label seen so far in an enclosing `switch`.
+struct SwitchContext{
+    values:Vec<i64>,
+    has_default:bool,
+}
+
+/// What an ordinary identifier names: an object, an enumeration constant or a type.
+#[derive(Debug,Clone)]
+enum Symbol{
+    Variable(GrammarVartype),
+    Enumerator(i64),
+    Typedef(GrammarVartype),
+}
+
+/// A tag declared by `struct`, `union` or `enum`, which all share one namespace.
+#[derive(Debug,Clone)]
+enum Tag{
+    Record(GrammarRecordtype),
+    Enum{tag:String,defined:bool},
+}
+
+impl std::fmt::Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tag::Record(record) => write!(f,"{}",record),
+            Tag::Enum{tag,..} => write!(f,"enum {}",tag),
+        }
+    }
+}
+
+/// One step building a declared type from the base type, in the order the steps apply.
+#[derive(Debug,Clone)]
+enum Derivation{
+    Pointer,
+    /// An array, without a length for `[]`.
+    Array(Option<usize>,TextSpan),
+    Function(Vec<ASTFunctonParam>),
+}
+
+/// The name and type a declarator declares, with the parameters when it declares a function.
+struct Declarator{
+    name_token:Option<Token>,
+    ty:GrammarVartype,
+    params:Option<Vec<ASTFunctonParam>>,
+    /// Whether the length of the outermost array is left for the initializer to give.
+    length_omitted:bool,
+}
+
 pub struct Parser{
     tokens: Vec<Token>,
     current: Counter,
     diagnostics_bag: DiagnosticsBagCell,
+    loop_depth: usize,
+    switches: Vec<SwitchContext>,
+    labels: Vec<String>,
+    gotos: Vec<Token>,
+    symbols: ScopeStack<Symbol>,
+    /// The `struct`, `union` and `enum` tags, which have a namespace of their own.
+    tags: ScopeStack<Tag>,
+    /// Whether this parses the condition of `#if`, where integer constants are as wide as possible.
+    is_directive: bool,
+    /// The return type of the function whose body is being parsed.
+    returntype: Option<GrammarVartype>,
+    /// The names of the functions defined so far.
+    functions: Vec<String>,
+    /// The names used to designate a function, which must be defined by the end of the program.
+    function_uses: Vec<Token>,
 }
 
 impl Parser {
     pub fn new(tokens:Vec<Token>,diagnostics_bag: DiagnosticsBagCell) ->Self{
         Self{
             tokens:tokens.iter().filter(
-                |token| token.kind!=TokenKind::WhiteSpace
+                |token| !token.kind.is_trivia()
             ).cloned().collect(),
             current:Counter::new(),
             diagnostics_bag,
+            loop_depth: 0,
+            switches: Vec::new(),
+            labels: Vec::new(),
+            gotos: Vec::new(),
+            symbols: ScopeStack::new(),
+            tags: ScopeStack::new(),
+            is_directive: false,
+            returntype: None,
+            functions: Vec::new(),
+            function_uses: Vec::new(),
         }
     }
     pub fn next_program_unit(&mut self) -> Option<ASTProgramunit>{
         if self.is_at_end() {
+            self.check_function_uses();
             return None;
         }
         Some(self.parse_program_unit())
     }
     pub fn parse_program_unit(&mut self) -> ASTProgramunit{
-        let token2 = self.peek(2);
-        match token2.kind { 
-            TokenKind::LeftParen =>{
-                let function = self.parse_function();
-                ASTProgramunit::function(function)
-            },
-            _ => {
-                let declaration_list  =  self.parse_declaration_list();
-                ASTProgramunit::declaration(declaration_list)
+        if self.current().kind == TokenKind::Typedef {
+            return ASTProgramunit::declaration(self.parse_declaration_list());
+        }
+        let vartype = self.parse_vartype();
+        if self.current().kind == TokenKind::SemiColon {
+            return ASTProgramunit::declaration(self.parse_declarators(vartype));
+        }
+        let declarator = self.parse_declarator(&vartype,false);
+        if declarator.params.is_some() && self.current().kind == TokenKind::LeftBrace {
+            return ASTProgramunit::function(self.parse_function(declarator));
+        }
+        ASTProgramunit::declaration(self.parse_init_declarators(vartype,declarator))
+    }
+    /// Reports the functions used without a definition, once each.
+    fn check_function_uses(&mut self){
+        let mut reported: Vec<String> = vec![];
+        for token in std::mem::take(&mut self.function_uses) {
+            let name = &token.span.literal;
+            if !self.functions.contains(name) && !reported.contains(name) {
+                self.diagnostics_bag.borrow_mut().report_error(format!("Undefined reference to function: {}",name),token.span.clone());
+                reported.push(name.clone());
             }
         }
-        
     }
     pub fn next_statement(&mut self) -> Option<ASTStatement>{
         if self.is_at_end() {
@@ -69,53 +162,401 @@ impl Parser {
     fn parse_statement(&mut self) -> ASTStatement{
         let token = self.current();
         match &token.kind { 
-            TokenKind::VarType(vartype) =>{
+            _ if self.is_declaration_start() =>{
                 let declaration_list  =  self.parse_declaration_list();
                 ASTStatement::declaration(declaration_list)
             },
             TokenKind::Return => {
-                self.consume();
+                let keyword = self.consume().unwrap().clone();
                 let next_token = self.current();
                 if let TokenKind::SemiColon = next_token.kind{
                     self.consume();
                     return ASTStatement::empty_return();
                 }
                 let expr = self.parse_expression();
-                self.consume();
+                self.consume_and_check(TokenKind::SemiColon);
+                if let Some(returntype) = self.returntype.clone() {
+                    let message = format!("Returning {} from a function with incompatible result type {}",expr.ty,returntype);
+                    self.check_assignable(&returntype,&expr,message,&keyword.span);
+                }
                 ASTStatement::return_statement(expr)
             }
+            TokenKind::If => self.parse_if_statement(),
+            TokenKind::While => self.parse_while_statement(),
+            TokenKind::Do => self.parse_do_while_statement(),
+            TokenKind::For => self.parse_for_statement(),
+            TokenKind::Break | TokenKind::Continue => {
+                let token = self.consume().unwrap().clone();
+                self.consume_and_check(TokenKind::SemiColon);
+                let in_switch = token.kind == TokenKind::Break && !self.switches.is_empty();
+                if self.loop_depth == 0 && !in_switch {
+                    self.diagnostics_bag.borrow_mut().report_error(
+                        format!("'{}' statement not in loop",token.span.literal),
+                        token.span.clone(),
+                    );
+                }
+                if token.kind == TokenKind::Break {
+                    ASTStatement::break_statement()
+                } else {
+                    ASTStatement::continue_statement()
+                }
+            }
+            TokenKind::Goto => {
+                self.consume();
+                let label_token = self.current().clone();
+                let label = self.parse_identifier();
+                self.consume_and_check(TokenKind::SemiColon);
+                self.gotos.push(label_token);
+                ASTStatement::goto_statement(label)
+            }
+            TokenKind::Identifier(_) if self.peek(1).kind == TokenKind::Colon => {
+                let label_token = self.current().clone();
+                let label = self.parse_identifier();
+                self.consume();
+                if self.labels.contains(&label) {
+                    self.diagnostics_bag.borrow_mut().report_error(
+                        format!("Redefinition of label: {}",label),
+                        label_token.span,
+                    );
+                }
+                self.labels.push(label.clone());
+                let statement = self.parse_statement();
+                ASTStatement::labeled(label,statement)
+            }
+            TokenKind::SemiColon => {
+                self.consume();
+                ASTStatement::empty()
+            }
+            TokenKind::LeftBrace => {
+                self.push_scope();
+                let statements = self.parse_braced_statements();
+                self.pop_scope();
+                ASTStatement::block(statements)
+            }
+            TokenKind::Switch => self.parse_switch_statement(),
+            TokenKind::Case => self.parse_case_statement(),
+            TokenKind::Default => {
+                let token = self.consume().unwrap().clone();
+                self.consume_and_check(TokenKind::Colon);
+                match self.switches.last_mut() {
+                    None => self.diagnostics_bag.borrow_mut().report_error(
+                        "'default' statement not in switch statement".to_string(),
+                        token.span.clone(),
+                    ),
+                    Some(switch) if switch.has_default => self.diagnostics_bag.borrow_mut().report_error(
+                        "Multiple default labels in one switch".to_string(),
+                        token.span.clone(),
+                    ),
+                    Some(switch) => switch.has_default = true,
+                }
+                let statement = self.parse_statement();
+                ASTStatement::default_statement(statement)
+            }
             _ => {
                 let expr = self.parse_expression();
-                self.consume();
+                self.consume_and_check(TokenKind::SemiColon);
                 ASTStatement::expression(expr)
             }
         }
     }
+    fn parse_condition(&mut self) -> ASTExpression{
+        self.consume_and_check(TokenKind::LeftParen);
+        let condition = self.parse_expression();
+        self.consume_and_check(TokenKind::RightParen);
+        condition
+    }
+    /// Parses the condition of a statement that tests it against 0.
+    fn parse_scalar_condition(&mut self,keyword:&Token) -> ASTExpression{
+        let condition = self.parse_condition();
+        self.check_scalar_condition(&condition,keyword);
+        condition
+    }
+    fn check_scalar_condition(&mut self,condition:&ASTExpression,keyword:&Token){
+        if !condition.ty.is_scalar() {
+            self.diagnostics_bag.borrow_mut().report_error(format!("Statement requires expression of scalar type: {}",condition.ty),keyword.span.clone());
+        }
+    }
+    fn parse_if_statement(&mut self) -> ASTStatement{
+        let keyword = self.consume().unwrap().clone();
+        let condition = self.parse_scalar_condition(&keyword);
+        let then_branch = self.parse_statement();
+        let mut else_branch = None;
+        if self.current().kind == TokenKind::Else {
+            self.consume();
+            else_branch = Some(self.parse_statement());
+        }
+        ASTStatement::if_statement(condition,then_branch,else_branch)
+    }
+    fn parse_while_statement(&mut self) -> ASTStatement{
+        let keyword = self.consume().unwrap().clone();
+        let condition = self.parse_scalar_condition(&keyword);
+        let body = self.parse_loop_body();
+        ASTStatement::while_statement(condition,body)
+    }
+    fn parse_do_while_statement(&mut self) -> ASTStatement{
+        self.consume();
+        let body = self.parse_loop_body();
+        let keyword = self.consume_and_check(TokenKind::While).clone();
+        let condition = self.parse_scalar_condition(&keyword);
+        self.consume_and_check(TokenKind::SemiColon);
+        ASTStatement::do_while_statement(body,condition)
+    }
+    fn parse_switch_statement(&mut self) -> ASTStatement{
+        let token = self.consume().unwrap().clone();
+        let condition = self.parse_condition();
+        if !condition.ty.is_integer() {
+            self.diagnostics_bag.borrow_mut().report_error(format!("Statement requires expression of integer type: {}",condition.ty),token.span);
+        }
+        self.switches.push(SwitchContext{values:Vec::new(),has_default:false});
+        let body = self.parse_statement();
+        self.switches.pop();
+        ASTStatement::switch_statement(condition,body)
+    }
+    fn parse_case_statement(&mut self) -> ASTStatement{
+        let token = self.consume().unwrap().clone();
+        let expr = self.parse_expression();
+        self.consume_and_check(TokenKind::Colon);
+        let value = match expr.constant_value() {
+            Some(value) => value,
+            None => {
+                self.diagnostics_bag.borrow_mut().report_error(
+                    "Case label is not an integer constant expression".to_string(),
+                    token.span.clone(),
+                );
+                0
+            }
+        };
+        match self.switches.last_mut() {
+            None => self.diagnostics_bag.borrow_mut().report_error(
+                "'case' statement not in switch statement".to_string(),
+                token.span.clone(),
+            ),
+            Some(switch) if switch.values.contains(&value) => self.diagnostics_bag.borrow_mut().report_error(
+                format!("Duplicate case value: {}",value),
+                token.span.clone(),
+            ),
+            Some(switch) => switch.values.push(value),
+        }
+        let statement = self.parse_statement();
+        ASTStatement::case_statement(expr,value,statement)
+    }
+    fn parse_for_statement(&mut self) -> ASTStatement{
+        self.push_scope();
+        let statement = self.parse_for_clauses();
+        self.pop_scope();
+        statement
+    }
+    fn parse_for_clauses(&mut self) -> ASTStatement{
+        let keyword = self.consume().unwrap().clone();
+        self.consume_and_check(TokenKind::LeftParen);
+        let init = match self.current().kind {
+            TokenKind::SemiColon => {
+                self.consume();
+                None
+            }
+            _ if self.is_declaration_start() => Some(ASTStatement::declaration(self.parse_declaration_list())),
+            _ => {
+                let expr = self.parse_expression();
+                self.consume_and_check(TokenKind::SemiColon);
+                Some(ASTStatement::expression(expr))
+            }
+        };
+        let condition = self.parse_optional_expression(TokenKind::SemiColon);
+        if let Some(condition) = &condition {
+            self.check_scalar_condition(condition,&keyword);
+        }
+        let step = self.parse_optional_expression(TokenKind::RightParen);
+        let body = self.parse_loop_body();
+        ASTStatement::for_statement(init,condition,step,body)
+    }
+    /// Parses an expression unless `end` comes first, then consumes `end`.
+    fn parse_optional_expression(&mut self,end:TokenKind) -> Option<ASTExpression>{
+        let mut expr = None;
+        if self.current().kind != end {
+            expr = Some(self.parse_expression());
+        }
+        self.consume_and_check(end);
+        expr
+    }
+    fn parse_loop_body(&mut self) -> ASTStatement{
+        self.loop_depth += 1;
+        let body = self.parse_statement();
+        self.loop_depth -= 1;
+        body
+    }
+    fn parse_braced_statements(&mut self) -> Vec<ASTStatement>{
+        self.consume_and_check(TokenKind::LeftBrace);
+        let mut statements = vec![];
+        loop {
+            let current = self.current();
+            if current.kind==TokenKind::RightBrace {
+                self.consume();
+                break;
+            }
+            if self.is_at_end() {
+                self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::RightBrace,current);
+                break;
+            }
+            statements.push(self.parse_statement());
+        }
+        statements
+    }
+    /// Enters `name` in the current scope, reporting a redefinition. Only variables may be
+    /// declared again at file scope, and typedefs when they name the same type.
+    fn declare_symbol(&mut self,name:&str,token:&Token,symbol:Symbol){
+        if name.is_empty() {
+            return;
+        }
+        let is_file_scope = self.symbols.is_file_scope();
+        let Some(previous) = self.symbols.declare(name.to_string(),symbol.clone()) else {
+            return;
+        };
+        let conflicts = match (&previous,&symbol) {
+            (Symbol::Variable(_),Symbol::Variable(_)) => !is_file_scope,
+            (Symbol::Typedef(previous),Symbol::Typedef(ty)) => previous != ty,
+            _ => true,
+        };
+        if conflicts {
+            // The first declaration stays in effect, so later uses are checked against it.
+            self.symbols.declare(name.to_string(),previous);
+            self.diagnostics_bag.borrow_mut().report_error(
+                format!("Redefinition of {}",name),
+                token.span.clone(),
+            );
+        }
+    }
+    fn parse_identifier(&mut self) -> String{
+        let token = self.consume().unwrap();
+        if let TokenKind::Identifier(name) = &token.kind {
+            name.clone()
+        } else {
+            self.diagnostics_bag.borrow_mut().report_expected_identifier(token);
+            String::new()
+        }
+    }
     fn parse_function_params(&mut self) -> ASTFunctonParam{
-        let var_type = self.parse_vartype().unwrap();
-        let name_token = self.consume().unwrap().clone();
-        if let TokenKind::Identifier(name)=name_token.kind{
-            ASTFunctonParam::new(var_type,name)
-        } else{
-            panic!()
-        }
-    }
-    fn parse_function(&mut self) -> ASTFunction {
-        let var_type = self.parse_vartype().unwrap();
-        let name_token = self.consume().unwrap().clone();
-        let mut function_name;
-        if let TokenKind::Identifier(name)=name_token.kind{
-            function_name = name;
-        } else{
-            panic!()
-        }
-        let left_paren = self.consume().unwrap();
-        if let TokenKind::LeftParen=left_paren.kind{
-        } else{
-            panic!()
+        let base = self.parse_vartype();
+        let declarator = self.parse_declarator(&base,true);
+        // A parameter declared as an array or a function is a pointer to it.
+        let var_type = declarator.ty.decay();
+        let name = match &declarator.name_token {
+            Some(token) => {
+                let name = token.span.literal.clone();
+                self.declare_symbol(&name,token,Symbol::Variable(var_type.clone()));
+                name
+            }
+            None => String::new(),
+        };
+        ASTFunctonParam::new(var_type,name)
+    }
+    /// Parses a declarator, as `*name[4]` or `(*name)(int, int)`, giving the type it derives
+    /// from `base`. The name is optional, so this also parses the abstract declarators of type names.
+    /// The length of the outermost array may be left out where the type is adjusted to a pointer.
+    fn parse_declarator(&mut self,base:&GrammarVartype,outer_may_be_unsized:bool) -> Declarator{
+        let mut name_token = None;
+        let derivations = self.parse_derivations(&mut name_token);
+        let mut ty = base.clone();
+        let mut params = None;
+        let mut length_omitted = false;
+        let count = derivations.len();
+        for (index,derivation) in derivations.into_iter().enumerate() {
+            params = None;
+            ty = match derivation {
+                Derivation::Pointer => GrammarVartype::reference(ty),
+                Derivation::Array(length,span) => {
+                    if matches!(ty,GrammarVartype::Function(_)) {
+                        self.diagnostics_bag.borrow_mut().report_error(format!("Declared as array of functions of type: {}",ty),span.clone());
+                    }
+                    let length = length.unwrap_or_else(|| {
+                        let is_outer = index + 1 == count;
+                        length_omitted = is_outer && self.current().kind == TokenKind::Equal;
+                        if !(is_outer && outer_may_be_unsized || length_omitted) {
+                            self.diagnostics_bag.borrow_mut().report_error("Array size missing".to_string(),span);
+                        }
+                        1
+                    });
+                    GrammarVartype::array(ty,length)
+                }
+                Derivation::Function(function_params) => {
+                    if ty.is_array() || matches!(ty,GrammarVartype::Function(_)) {
+                        let span = name_token.as_ref().map_or_else(|| self.current().span.clone(),|token: &Token| token.span.clone());
+                        self.diagnostics_bag.borrow_mut().report_error(format!("Function cannot return type: {}",ty),span);
+                    }
+                    let arguments = function_params.iter().map(|param| param.param_type.clone()).collect();
+                    params = Some(function_params);
+                    GrammarVartype::function(arguments,ty)
+                }
+            };
+        }
+        Declarator{name_token,ty,params,length_omitted}
+    }
+    /// Parses the pointers, the name or parenthesized inner declarator, and the suffixes of a declarator.
+    /// The pointers bind to the base first, then the suffixes from the right, and the inner declarator last.
+    fn parse_derivations(&mut self,name_token:&mut Option<Token>) -> Vec<Derivation>{
+        let mut derivations = vec![];
+        while self.current().kind == TokenKind::Asterisk {
+            self.consume();
+            derivations.push(Derivation::Pointer);
+        }
+        let inner = if self.current().kind == TokenKind::LeftParen && self.is_nested_declarator() {
+            self.consume();
+            let inner = self.parse_derivations(name_token);
+            self.consume_and_check(TokenKind::RightParen);
+            inner
+        } else {
+            if let TokenKind::Identifier(_) = self.current().kind {
+                *name_token = Some(self.consume().unwrap().clone());
+            }
+            vec![]
+        };
+        let mut suffixes = vec![];
+        loop {
+            match self.current().kind {
+                TokenKind::LeftBracket => suffixes.push(self.parse_array_suffix()),
+                TokenKind::LeftParen => suffixes.push(Derivation::Function(self.parse_parameter_list())),
+                _ => break,
+            }
+        }
+        derivations.extend(suffixes.into_iter().rev());
+        derivations.extend(inner);
+        derivations
+    }
+    /// Whether the `(` at the current token starts a parenthesized declarator rather than a parameter list.
+    fn is_nested_declarator(&self) -> bool{
+        match &self.peek(1).kind {
+            TokenKind::Asterisk | TokenKind::LeftParen => true,
+            TokenKind::Identifier(name) => self.typedef_name(name).is_none(),
+            _ => false,
         }
+    }
+    /// Parses one `[N]` or `[]` suffix of a declarator.
+    fn parse_array_suffix(&mut self) -> Derivation{
+        let bracket = self.consume().unwrap().clone();
+        if self.current().kind == TokenKind::RightBracket {
+            self.consume();
+            return Derivation::Array(None,bracket.span);
+        }
+        let size = self.parse_conditional_expression();
+        self.consume_and_check(TokenKind::RightBracket);
+        let length = match size.constant_value() {
+            Some(length) if length >= 0 => length as usize,
+            Some(length) => {
+                self.diagnostics_bag.borrow_mut().report_error(format!("Array has negative size: {}",length),bracket.span.clone());
+                1
+            }
+            None => {
+                self.diagnostics_bag.borrow_mut().report_error("Array size is not an integer constant expression".to_string(),bracket.span.clone());
+                1
+            }
+        };
+        Derivation::Array(Some(length),bracket.span)
+    }
+    /// Parses `(params)` of a function declarator. The names are only visible inside the list;
+    /// a function definition declares them again for its body.
+    fn parse_parameter_list(&mut self) -> Vec<ASTFunctonParam>{
+        self.consume();
+        self.push_scope();
         let mut params = vec![];
-        let mut params_type = vec![];
         loop {
             let current_token  = self.current();
             match current_token.kind { 
@@ -123,97 +564,642 @@ impl Parser {
                     self.consume();
                     break;
                 },
+                TokenKind::Eof => {
+                    self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::RightParen,current_token);
+                    break;
+                }
                 TokenKind::Comma => {
                     self.consume();
-                    let param = self.parse_function_params();
-                    let param_type = param.param_type.clone();
-                    params.push(param);
-                    params_type.push(param_type);
+                    params.push(self.parse_function_params());
                 }
                 _ => {
-                    let param = self.parse_function_params();
-                    let param_type = param.param_type.clone();
-                    params.push(param);
-                    params_type.push(param_type);
+                    params.push(self.parse_function_params());
                 }
             }
         }
-        let left_brace = self.consume().unwrap();
-        if let TokenKind::LeftBrace=left_brace.kind{
-        } else{
-            panic!()
+        self.pop_scope();
+        params
+    }
+    /// The name a declaration requires, reporting its absence.
+    fn declarator_name(&mut self,declarator:&Declarator) -> Option<(String,Token)>{
+        if let Some(token) = &declarator.name_token {
+            return Some((token.span.literal.clone(),token.clone()));
         }
-        let function_type = GrammarFunctiontype::new(params_type,var_type);
-        let mut function =ASTFunction::new(function_type,params);
+        let token = self.current().clone();
+        self.diagnostics_bag.borrow_mut().report_expected_identifier(&token);
+        if !matches!(token.kind, TokenKind::SemiColon | TokenKind::Comma | TokenKind::Equal | TokenKind::Eof) {
+            self.consume();
+        }
+        None
+    }
+    fn parse_function(&mut self,declarator:Declarator) -> ASTFunction {
+        let GrammarVartype::Function(function_type) = declarator.ty.clone() else {
+            unreachable!("A function definition has a function declarator");
+        };
+        let function_name = declarator.name_token.as_ref().map_or_else(String::new,|token| token.span.literal.clone());
+        if let Some(token) = &declarator.name_token {
+            self.declare_symbol(&function_name,token,Symbol::Variable(declarator.ty.clone()));
+            if self.functions.contains(&function_name) {
+                self.diagnostics_bag.borrow_mut().report_error(format!("Redefinition of {}",function_name),token.span.clone());
+            }
+            self.functions.push(function_name.clone());
+        }
+        let params = declarator.params.unwrap_or_default();
+        self.push_scope();
+        for param in params.iter().filter(|param| !param.name.is_empty()) {
+            self.symbols.declare(param.name.clone(),Symbol::Variable(param.param_type.clone()));
+        }
+        let mut function =ASTFunction::new(*function_type,params);
         function.name = function_name;
-        loop {
-            let statement = self.parse_statement();
-            function.statements.push(statement);
-            let current = self.current();
-            if(current.kind==TokenKind::RightBrace) {
-                self.consume();
-                break;
+        self.returntype = Some(function.function_type().returntype.clone());
+        function.statements = self.parse_braced_statements();
+        self.returntype = None;
+        self.pop_scope();
+        for goto in std::mem::take(&mut self.gotos) {
+            if let TokenKind::Identifier(label) = &goto.kind {
+                if !self.labels.contains(label) {
+                    self.diagnostics_bag.borrow_mut().report_error(
+                        format!("Use of undeclared label: {}",label),
+                        goto.span.clone(),
+                    );
+                }
             }
         }
+        self.labels.clear();
         function
     }
     fn parse_declaration_list(&mut self) -> ASTDeclarationList{
-        let vartype = self.parse_vartype().unwrap();
+        if self.current().kind == TokenKind::Typedef {
+            self.consume();
+            let vartype = self.parse_vartype();
+            return self.parse_typedef_declarators(vartype);
+        }
+        let vartype = self.parse_vartype();
+        self.parse_declarators(vartype)
+    }
+    /// Parses the declarators of `typedef type name, ...;`, entering each name as a type.
+    /// Nothing is left for the evaluator, so the returned list is empty.
+    fn parse_typedef_declarators(&mut self,vartype:GrammarVartype) -> ASTDeclarationList{
+        let declaration_list = ASTDeclarationList::new(vartype);
+        if self.current().kind == TokenKind::SemiColon {
+            self.consume();
+            return declaration_list;
+        }
+        loop {
+            let declarator = self.parse_declarator(&declaration_list.vartype,false);
+            if let Some((name,name_token)) = self.declarator_name(&declarator) {
+                self.declare_symbol(&name,&name_token,Symbol::Typedef(declarator.ty));
+            }
+            if self.current().kind == TokenKind::Equal {
+                let equal = self.consume().unwrap().clone();
+                self.diagnostics_bag.borrow_mut().report_error("Illegal initializer in typedef".to_string(),equal.span);
+                self.parse_assignment_expression();
+            }
+            if self.current().kind != TokenKind::Comma {
+                break;
+            }
+            self.consume();
+        }
+        self.consume_and_check(TokenKind::SemiColon);
+        declaration_list
+    }
+    /// Parses the declarators following the type of a declaration, up to its `;`.
+    /// There may be none when the type itself declares something, as `struct s { ... };`
+    /// or `enum e { ... };`.
+    fn parse_declarators(&mut self,vartype:GrammarVartype) -> ASTDeclarationList{
+        let declaration_list = ASTDeclarationList::new(vartype);
+        let declares_type = declaration_list.vartype.is_record()
+            || self.peek(-1).kind == TokenKind::RightBrace
+            || self.peek(-2).kind == TokenKind::Enum;
+        if self.current().kind == TokenKind::SemiColon && declares_type {
+            self.consume();
+            return declaration_list;
+        }
+        let declarator = self.parse_declarator(&declaration_list.vartype,false);
+        self.parse_init_declarators(declaration_list.vartype,declarator)
+    }
+    /// Parses the rest of a declaration whose first declarator has been parsed already.
+    fn parse_init_declarators(&mut self,vartype:GrammarVartype,first:Declarator) -> ASTDeclarationList{
         let mut declaration_list = ASTDeclarationList::new(vartype);
+        let mut declarator = first;
         loop{
-            let declaration = self.parse_declararion();
-            declaration_list.declare_list.push(declaration);
-            let current = self.consume().unwrap();
-            if(current.kind!=TokenKind::Comma) {
+            if let Some(declaration) = self.parse_declararion(declarator) {
+                declaration_list.declare_list.push(declaration);
+            }
+            if self.current().kind!=TokenKind::Comma {
                 break;
             }
+            self.consume();
+            declarator = self.parse_declarator(&declaration_list.vartype,false);
         }
+        self.consume_and_check(TokenKind::SemiColon);
         declaration_list
     }
     
-    fn parse_declararion(&mut self) -> ASTDeclaration{
-        let var = self.consume().unwrap().clone();
-        let ahead1 = self.current();
-        match &ahead1.kind {
-            TokenKind::Comma|TokenKind::SemiColon => {
-                if let TokenKind::Identifier(name)=var.kind{
-                    self.consume();
-                    return  ASTDeclaration::VariableDeclareDirect(name);
-                } else{
-                    panic!()
-                }
-            },
-            TokenKind::Equal => {
-                self.consume();
-                let expr = self.parse_expression();
-                if let TokenKind::Identifier(name)=var.kind{
-                    return  ASTDeclaration::VariableDeclareWithInit(name,expr);
-                } else{
-                    panic!()
+    /// Declares what `declarator` names, with its initializer. A function declaration
+    /// only enters the name, leaving nothing for the evaluator.
+    fn parse_declararion(&mut self,declarator:Declarator) -> Option<ASTDeclaration>{
+        let (name,name_token) = self.declarator_name(&declarator)?;
+        let vartype = declarator.ty;
+        if let GrammarVartype::Function(_) = vartype {
+            self.declare_symbol(&name,&name_token,Symbol::Variable(vartype));
+            if self.current().kind == TokenKind::Equal {
+                let equal = self.consume().unwrap().clone();
+                self.diagnostics_bag.borrow_mut().report_error(format!("Illegal initializer for function: {}",name),equal.span);
+                self.parse_assignment_expression();
+            }
+            return None;
+        }
+        self.check_complete(&vartype,&name_token);
+        self.declare_symbol(&name,&name_token,Symbol::Variable(vartype.clone()));
+        if self.current().kind == TokenKind::Equal {
+            let equal = self.consume().unwrap().clone();
+            let expr = self.parse_assignment_expression();
+            if !vartype.is_array() {
+                let message = format!("Initializing {} with an expression of incompatible type {}",vartype,expr.ty);
+                if !self.check_assignable(&vartype,&expr,message,&equal.span) {
+                    return None;
                 }
-            },
-            _ => {
-                panic!()
+                return Some(ASTDeclaration::VariableDeclareWithInit(name,vartype,expr));
+            }
+            let vartype = self.check_array_initializer(vartype,declarator.length_omitted,&expr,&equal)?;
+            if declarator.length_omitted {
+                // The length comes from the initializer.
+                self.symbols.declare(name.clone(),Symbol::Variable(vartype.clone()));
             }
+            Some(ASTDeclaration::VariableDeclareWithInit(name,vartype,expr))
+        } else {
+            Some(ASTDeclaration::VariableDeclareDirect(name,vartype))
         }
     }
     
     fn parse_expression(&mut self) -> ASTExpression{
         self.parse_binary_expression(0)
     }
+
+    /// Parses an expression without assignment or comma operators, as an array size.
+    fn parse_conditional_expression(&mut self) -> ASTExpression{
+        self.parse_binary_expression(CONDITIONAL_PRECEDENCE)
+    }
+
+    /// Parses an expression that stops at a top-level comma, as an argument or an initializer.
+    fn parse_assignment_expression(&mut self) -> ASTExpression{
+        self.parse_binary_expression(ASSIGNMENT_PRECEDENCE)
+    }
+
+    /// Parses an expression that must use up every token, as the condition of `#if`.
+    pub fn parse_constant_expression(&mut self) -> ASTExpression{
+        self.is_directive = true;
+        let expr = self.parse_expression();
+        if !self.is_at_end() {
+            self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::Eof,self.current());
+        }
+        expr
+    }
     
-    fn parse_vartype(&mut self) -> Option<GrammarVartype>{
+    /// Whether `kind` begins a type: a type keyword or an identifier declared by `typedef`.
+    fn is_type_start(&self,kind:&TokenKind) -> bool{
+        match kind {
+            TokenKind::VarType(_) | TokenKind::Struct | TokenKind::Union | TokenKind::Enum => true,
+            TokenKind::Short | TokenKind::Long | TokenKind::Signed | TokenKind::Unsigned => true,
+            TokenKind::Identifier(name) => self.typedef_name(name).is_some(),
+            _ => false,
+        }
+    }
+    /// The type `name` stands for when it is a typedef name in the current scope.
+    fn typedef_name(&self,name:&str) -> Option<GrammarVartype>{
+        match self.symbols.lookup(name) {
+            Some(Symbol::Typedef(ty)) => Some(ty.clone()),
+            _ => None,
+        }
+    }
+    /// Whether a declaration starts here rather than an expression or a label.
+    fn is_declaration_start(&self) -> bool{
+        let kind = &self.current().kind;
+        *kind == TokenKind::Typedef || (self.is_type_start(kind) && self.peek(1).kind != TokenKind::Colon)
+    }
+    /// Checks a string literal initializing an array, giving the array its length when it was left out.
+    fn check_array_initializer(&mut self,vartype:GrammarVartype,length_omitted:bool,expr:&ASTExpression,equal:&Token) -> Option<GrammarVartype>{
+        let GrammarVartype::Array(element,length) = &vartype else {
+            return Some(vartype);
+        };
+        let message = match (&expr.kind,&expr.ty) {
+            (ASTExpressionKind::String(_),GrammarVartype::Array(_,literal_length)) => match **element {
+                GrammarVartype::Direct(VartypeKind::Char | VartypeKind::SignedChar | VartypeKind::UnsignedChar) => {
+                    if length_omitted {
+                        return Some(GrammarVartype::array((**element).clone(),*literal_length));
+                    }
+                    // The terminating NUL is dropped when only it does not fit.
+                    if *literal_length > length + 1 {
+                        "Initializer-string for char array is too long".to_string()
+                    } else {
+                        return Some(vartype);
+                    }
+                }
+                _ => format!("Cannot initialize an array of {} with a string literal",element),
+            },
+            (ASTExpressionKind::Error(_),_) => return None,
+            _ => "Array initializer must be an initializer list or string literal".to_string(),
+        };
+        self.diagnostics_bag.borrow_mut().report_error(message,equal.span.clone());
+        None
+    }
+    /// Reports an object declared with a type whose size is not known.
+    fn check_complete(&mut self,vartype:&GrammarVartype,token:&Token){
+        let mut element = vartype;
+        while let GrammarVartype::Array(inner,_) = element {
+            element = inner;
+        }
+        if let GrammarVartype::Record(record) = element {
+            if !record.is_complete() {
+                self.diagnostics_bag.borrow_mut().report_error(
+                    format!("Variable has incomplete type: {}",vartype),
+                    token.span.clone(),
+                );
+            }
+        }
+    }
+    fn push_scope(&mut self){
+        self.symbols.push();
+        self.tags.push();
+    }
+    fn pop_scope(&mut self){
+        self.symbols.pop();
+        self.tags.pop();
+    }
+    /// Parses `struct tag`, `struct tag { ... }` or `struct { ... }`, and the same for `union`.
+    fn parse_record_specifier(&mut self,kind:GrammarRecordKind) -> GrammarVartype{
+        let tag_token = match self.current().kind {
+            TokenKind::Identifier(_) => Some(self.consume().unwrap().clone()),
+            _ => None,
+        };
+        let tag = tag_token.as_ref().map(|token| token.span.literal.clone());
+        let is_definition = self.current().kind == TokenKind::LeftBrace;
+        let record = match (&tag,&tag_token) {
+            (Some(tag),Some(token)) => {
+                // A definition or `struct tag;` declares a new type in the current scope,
+                // other uses refer to the visible one, declaring it when there is none.
+                let existing = if is_definition || self.current().kind == TokenKind::SemiColon {
+                    self.tags.is_declared_in_current_scope(tag).then(|| self.tags.lookup(tag).cloned()).flatten()
+                } else {
+                    self.tags.lookup(tag).cloned()
+                };
+                match existing {
+                    Some(other @ Tag::Enum{..}) => {
+                        self.diagnostics_bag.borrow_mut().report_error(
+                            format!("Use of {} with tag type that does not match previous declaration: {}",tag,other),
+                            token.span.clone(),
+                        );
+                        GrammarRecordtype::new(kind,Some(tag.clone()))
+                    }
+                    Some(Tag::Record(record)) => {
+                        if record.kind() != kind {
+                            self.diagnostics_bag.borrow_mut().report_error(
+                                format!("Use of {} with tag type that does not match previous declaration: {}",tag,record),
+                                token.span.clone(),
+                            );
+                        } else if is_definition && record.is_complete() {
+                            self.diagnostics_bag.borrow_mut().report_error(format!("Redefinition of {}",record),token.span.clone());
+                        }
+                        record
+                    }
+                    None => {
+                        let record = GrammarRecordtype::new(kind,Some(tag.clone()));
+                        self.tags.declare(tag.clone(),Tag::Record(record.clone()));
+                        record
+                    }
+                }
+            }
+            _ => {
+                if !is_definition {
+                    self.diagnostics_bag.borrow_mut().report_expected_identifier(self.current());
+                }
+                GrammarRecordtype::new(kind,None)
+            }
+        };
+        if is_definition {
+            let members = self.parse_record_members();
+            if !record.is_complete() {
+                record.complete(members);
+            }
+        }
+        GrammarVartype::Record(record)
+    }
+    /// Parses the rest of `enum tag { A, B = 5, ... }` or `enum tag`. An enum is an `int`
+    /// whose enumerators are constants declared alongside the variables of the scope.
+    fn parse_enum_specifier(&mut self) -> GrammarVartype{
+        let tag_token = match self.current().kind {
+            TokenKind::Identifier(_) => Some(self.consume().unwrap().clone()),
+            _ => None,
+        };
+        let is_definition = self.current().kind == TokenKind::LeftBrace;
+        match &tag_token {
+            Some(token) => {
+                let tag = token.span.literal.clone();
+                // Like `struct tag;`, `enum tag;` declares the tag ahead of its definition.
+                let is_declaration = is_definition || self.current().kind == TokenKind::SemiColon;
+                let existing = if is_declaration {
+                    self.tags.is_declared_in_current_scope(&tag).then(|| self.tags.lookup(&tag).cloned()).flatten()
+                } else {
+                    self.tags.lookup(&tag).cloned()
+                };
+                let message = match existing {
+                    Some(other @ Tag::Record(_)) => Some(format!("Use of {} with tag type that does not match previous declaration: {}",tag,other)),
+                    Some(other @ Tag::Enum{defined:true,..}) if is_definition => Some(format!("Redefinition of {}",other)),
+                    Some(Tag::Enum{..}) if !is_definition => None,
+                    Some(Tag::Enum{..}) => {
+                        self.tags.declare(tag.clone(),Tag::Enum{tag:tag.clone(),defined:true});
+                        None
+                    }
+                    None if is_declaration => {
+                        self.tags.declare(tag.clone(),Tag::Enum{tag:tag.clone(),defined:is_definition});
+                        None
+                    }
+                    None => Some(format!("Use of undeclared enum: {}",tag)),
+                };
+                if let Some(message) = message {
+                    self.diagnostics_bag.borrow_mut().report_error(message,token.span.clone());
+                }
+            }
+            None if !is_definition => self.diagnostics_bag.borrow_mut().report_expected_identifier(self.current()),
+            None => {}
+        }
+        if is_definition {
+            self.parse_enumerators();
+        }
+        GrammarVartype::int()
+    }
+    /// Parses `{ A, B = 5, ... }`, declaring each enumerator as soon as its value is known
+    /// so that later values may refer to it.
+    fn parse_enumerators(&mut self){
+        let brace = self.consume_and_check(TokenKind::LeftBrace).clone();
+        if self.current().kind == TokenKind::RightBrace {
+            self.diagnostics_bag.borrow_mut().report_error("Use of empty enum".to_string(),brace.span);
+        }
+        let mut value: i64 = 0;
+        while self.current().kind != TokenKind::RightBrace {
+            if self.is_at_end() {
+                self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::RightBrace,self.current());
+                return;
+            }
+            let name_token = self.current().clone();
+            let name = self.parse_identifier();
+            if self.current().kind == TokenKind::Equal {
+                self.consume();
+                let expr = self.parse_conditional_expression();
+                match expr.constant_value() {
+                    Some(constant) => value = constant,
+                    None if matches!(expr.kind,ASTExpressionKind::Error(_)) => {}
+                    None => self.diagnostics_bag.borrow_mut().report_error(
+                        "Enumerator value is not an integer constant expression".to_string(),
+                        name_token.span.clone(),
+                    ),
+                }
+            }
+            if i32::try_from(value).is_err() {
+                self.diagnostics_bag.borrow_mut().report_error(
+                    format!("Enumerator value is not representable in int: {}",value),
+                    name_token.span.clone(),
+                );
+            }
+            self.declare_symbol(&name,&name_token,Symbol::Enumerator(value));
+            value = value.wrapping_add(1);
+            if self.current().kind != TokenKind::Comma {
+                break;
+            }
+            self.consume();
+        }
+        self.consume_and_check(TokenKind::RightBrace);
+    }
+    /// Parses `{ member-declarations }`, returning the members in order.
+    fn parse_record_members(&mut self) -> Vec<(Option<String>,GrammarVartype)>{
+        self.consume_and_check(TokenKind::LeftBrace);
+        let mut members: Vec<(Option<String>,GrammarVartype)> = vec![];
+        while self.current().kind != TokenKind::RightBrace {
+            if self.is_at_end() {
+                self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::RightBrace,self.current());
+                return members;
+            }
+            let base = self.parse_vartype();
+            if self.current().kind == TokenKind::SemiColon {
+                self.consume();
+                // An untagged struct or union without a name lends its members to the enclosing one.
+                if let GrammarVartype::Record(record) = &base {
+                    if record.tag().is_none() {
+                        members.push((None,base));
+                    }
+                }
+                continue;
+            }
+            loop {
+                let declarator = self.parse_declarator(&base,false);
+                let Some((name,name_token)) = self.declarator_name(&declarator) else {
+                    break;
+                };
+                let vartype = declarator.ty;
+                if let GrammarVartype::Function(_) = vartype {
+                    self.diagnostics_bag.borrow_mut().report_error(format!("Field {} declared as a function",name),name_token.span.clone());
+                }
+                self.check_complete(&vartype,&name_token);
+                let names = members.iter().flat_map(|(name,ty)| match (name,ty) {
+                    (Some(name),_) => vec![name.clone()],
+                    (None,GrammarVartype::Record(record)) => record.members().into_iter().map(|member| member.name).collect(),
+                    _ => vec![],
+                }).collect::<Vec<_>>();
+                if names.contains(&name) {
+                    self.diagnostics_bag.borrow_mut().report_error(format!("Duplicate member: {}",name),name_token.span.clone());
+                }
+                members.push((Some(name),vartype));
+                if self.current().kind != TokenKind::Comma {
+                    break;
+                }
+                self.consume();
+            }
+            self.consume_and_check(TokenKind::SemiColon);
+        }
+        self.consume();
+        members
+    }
+    fn parse_vartype(&mut self) -> GrammarVartype{
+        if Self::is_arithmetic_specifier(&self.current().kind) {
+            return self.parse_arithmetic_specifiers();
+        }
         let token = self.current();
         self.consume();
         match &token.kind {
-            TokenKind::VarType(vartype) =>{
-               Some(GrammarVartype::new(vartype))
+            TokenKind::Struct => self.parse_record_specifier(GrammarRecordKind::Struct),
+            TokenKind::Union => self.parse_record_specifier(GrammarRecordKind::Union),
+            TokenKind::Enum => self.parse_enum_specifier(),
+            TokenKind::Identifier(name) if self.typedef_name(name).is_some() => self.typedef_name(name).unwrap(),
+            _ => {
+                self.diagnostics_bag.borrow_mut().report_expected_type(token);
+                GrammarVartype::new(&VartypeKind::Int)
+            }
+        }
+    }
+
+    fn is_arithmetic_specifier(kind:&TokenKind) -> bool{
+        matches!(kind, TokenKind::VarType(_) | TokenKind::Short | TokenKind::Long | TokenKind::Signed | TokenKind::Unsigned)
+    }
+    /// Parses a run of keywords naming an arithmetic type, in any order, as `unsigned long long`
+    /// or `long unsigned int`, reporting combinations that name no type.
+    fn parse_arithmetic_specifiers(&mut self) -> GrammarVartype{
+        let mut base: Option<VartypeKind> = None;
+        let (mut signed,mut unsigned,mut short,mut long) = (false,false,false,0);
+        while Self::is_arithmetic_specifier(&self.current().kind) {
+            let token = self.consume().unwrap().clone();
+            let conflict = match &token.kind {
+                TokenKind::VarType(kind) => base.replace(kind.clone()).is_some(),
+                TokenKind::Signed => std::mem::replace(&mut signed,true) || unsigned,
+                TokenKind::Unsigned => std::mem::replace(&mut unsigned,true) || signed,
+                TokenKind::Short => std::mem::replace(&mut short,true) || long > 0,
+                _ => {
+                    long += 1;
+                    short || long > 2
+                }
+            };
+            if conflict {
+                self.diagnostics_bag.borrow_mut().report_error(
+                    format!("Cannot combine with previous declaration specifier: {}",token.span.literal),
+                    token.span.clone(),
+                );
+            }
+        }
+        let kind = match base {
+            Some(VartypeKind::Char) if signed => VartypeKind::SignedChar,
+            Some(VartypeKind::Char) if unsigned => VartypeKind::UnsignedChar,
+            Some(VartypeKind::Char) => VartypeKind::Char,
+            // `long double` is kept as a `double`.
+            Some(VartypeKind::Double) => VartypeKind::Double,
+            Some(VartypeKind::Float) => VartypeKind::Float,
+            _ => match (short,long,unsigned) {
+                (true,_,false) => VartypeKind::Short,
+                (true,_,true) => VartypeKind::UnsignedShort,
+                (false,0,false) => VartypeKind::Int,
+                (false,0,true) => VartypeKind::UnsignedInt,
+                (false,1,false) => VartypeKind::Long,
+                (false,1,true) => VartypeKind::UnsignedLong,
+                (false,_,false) => VartypeKind::LongLong,
+                (false,_,true) => VartypeKind::UnsignedLongLong,
             },
+        };
+        let modified = signed || unsigned || short || long > 0;
+        let invalid = match base {
+            Some(VartypeKind::Char) => short || long > 0,
+            Some(VartypeKind::Float) => modified,
+            Some(VartypeKind::Double) => signed || unsigned || short || long > 1,
+            _ => false,
+        };
+        if invalid {
+            let token = self.peek(-1).clone();
+            self.diagnostics_bag.borrow_mut().report_error(
+                format!("Invalid combination of type specifiers for: {}",GrammarVartype::Direct(kind.clone())),
+                token.span,
+            );
+        }
+        GrammarVartype::Direct(kind)
+    }
+    /// Works out the value and type of an integer constant from its spelling: the first of
+    /// the types its suffix and radix allow that can represent the value.
+    fn parse_integer_literal(&mut self,token:&Token) -> ASTExpression{
+        let literal = token.span.literal.as_str();
+        let (radix,digits) = if let Some(hex) = literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+            (16,hex)
+        } else if literal.starts_with('0') {
+            (8,literal)
+        } else {
+            (10,literal)
+        };
+        let end = digits.find(|c:char| !c.is_digit(radix)).unwrap_or(digits.len());
+        let (digits,suffix) = digits.split_at(end);
+        let value = u64::from_str_radix(digits,radix);
+        let unsuffixed = suffix.trim_start_matches(['u','U']);
+        let unsuffixed = if unsuffixed.len() == suffix.len() { suffix.trim_end_matches(['u','U']) } else { unsuffixed };
+        let is_unsigned = unsuffixed.len() < suffix.len();
+        let longs = match unsuffixed {
+            "" => 0,
+            "l" | "L" => 1,
+            "ll" | "LL" => 2,
             _ => {
-                None
+                self.diagnostics_bag.borrow_mut().report_error(format!("Invalid suffix on integer constant: {}",suffix),token.span.clone());
+                return ASTExpression::error(token.span.clone());
+            }
+        };
+        let value = match value {
+            Ok(value) => value,
+            // Only `0x` without digits fails otherwise.
+            Err(_) if !digits.is_empty() || radix == 8 => {
+                self.diagnostics_bag.borrow_mut().report_error("Integer literal is too large to be represented in any integer type".to_string(),token.span.clone());
+                return ASTExpression::error(token.span.clone());
+            }
+            Err(_) => {
+                self.diagnostics_bag.borrow_mut().report_error("Invalid integer constant".to_string(),token.span.clone());
+                return ASTExpression::error(token.span.clone());
+            }
+        };
+        // In `#if` every integer type has the width of the largest one.
+        let candidates: &[VartypeKind] = match (self.is_directive,is_unsigned,longs,radix) {
+            (true,false,_,_) => &[VartypeKind::Long,VartypeKind::UnsignedLong],
+            (true,true,_,_) => &[VartypeKind::UnsignedLong],
+            (false,false,0,10) => &[VartypeKind::Int,VartypeKind::Long],
+            (false,false,0,_) => &[VartypeKind::Int,VartypeKind::UnsignedInt,VartypeKind::Long,VartypeKind::UnsignedLong],
+            (false,false,1,10) => &[VartypeKind::Long],
+            (false,false,1,_) => &[VartypeKind::Long,VartypeKind::UnsignedLong],
+            (false,false,_,10) => &[VartypeKind::LongLong],
+            (false,false,_,_) => &[VartypeKind::LongLong,VartypeKind::UnsignedLongLong],
+            (false,true,0,_) => &[VartypeKind::UnsignedInt,VartypeKind::UnsignedLong],
+            (false,true,1,_) => &[VartypeKind::UnsignedLong],
+            (false,true,_,_) => &[VartypeKind::UnsignedLongLong],
+        };
+        let fits = |kind:&VartypeKind| {
+            let ty = GrammarVartype::Direct(kind.clone());
+            let bits = 8 * ty.size() as u32 - (!ty.is_unsigned()) as u32;
+            bits == 64 || value < 1u64 << bits
+        };
+        match candidates.iter().find(|kind| fits(kind)) {
+            Some(kind) => ASTExpression::typed_number(value as i64,GrammarVartype::Direct(kind.clone())),
+            None => {
+                // A decimal constant too large for a signed type ends up unsigned, as with GCC.
+                ASTExpression::typed_number(value as i64,GrammarVartype::Direct(VartypeKind::UnsignedLongLong))
             }
         }
     }
+    /// Checks the spelling of a floating constant and works out its type from the suffix:
+    /// `float` for `f`, `double` otherwise, `long double` being the same as `double` here.
+    fn parse_floating_literal(&mut self,token:&Token,value:f64) -> ASTExpression{
+        let literal = token.span.literal.as_str();
+        let (hex,digits) = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+            Some(digits) => (true,digits),
+            None => (false,literal),
+        };
+        let mantissa = digits.find(|c:char| !(c == '.' || if hex { c.is_ascii_hexdigit() } else { c.is_ascii_digit() })).unwrap_or(digits.len());
+        let mut end = mantissa;
+        let message = if digits[end..].starts_with(if hex { ['p','P'] } else { ['e','E'] }) {
+            end += 1;
+            end += digits[end..].starts_with(['+','-']) as usize;
+            let exponent = digits[end..].find(|c:char| !c.is_ascii_digit()).unwrap_or(digits.len() - end);
+            end += exponent;
+            (exponent == 0).then_some("Exponent has no digits")
+        } else {
+            hex.then_some("Hexadecimal floating constant requires an exponent")
+        };
+        let suffix = &digits[end..];
+        let ty = match suffix {
+            "f" | "F" => VartypeKind::Float,
+            "" | "l" | "L" => VartypeKind::Double,
+            _ => {
+                self.diagnostics_bag.borrow_mut().report_error(format!("Invalid suffix on floating constant: {}",suffix),token.span.clone());
+                return ASTExpression::error(token.span.clone());
+            }
+        };
+        let message = message.or_else(|| value.is_nan().then_some("Invalid floating constant"));
+        if let Some(message) = message {
+            self.diagnostics_bag.borrow_mut().report_error(message.to_string(),token.span.clone());
+            return ASTExpression::error(token.span.clone());
+        }
+        let ty = GrammarVartype::Direct(ty);
+        ASTExpression::floating(Value::Floating(value).convert(&ty,&ty).as_floating(),ty)
+    }
 
     fn parse_binary_operator(&mut self) -> Option<ASTBinaryOperator>{
         let token = self.current();
@@ -222,16 +1208,129 @@ impl Parser {
             TokenKind::Minus => Some(ASTBinaryOperatorKind::Sub),
             TokenKind::Asterisk=> Some(ASTBinaryOperatorKind::Mul),
             TokenKind::Slash => Some(ASTBinaryOperatorKind::Div),
+            TokenKind::Percent => Some(ASTBinaryOperatorKind::Mod),
+            TokenKind::LessLess => Some(ASTBinaryOperatorKind::ShiftLeft),
+            TokenKind::GreaterGreater => Some(ASTBinaryOperatorKind::ShiftRight),
+            TokenKind::Less => Some(ASTBinaryOperatorKind::Less),
+            TokenKind::LessEqual => Some(ASTBinaryOperatorKind::LessEqual),
+            TokenKind::Greater => Some(ASTBinaryOperatorKind::Greater),
+            TokenKind::GreaterEqual => Some(ASTBinaryOperatorKind::GreaterEqual),
+            TokenKind::EqualEqual => Some(ASTBinaryOperatorKind::EqualEqual),
+            TokenKind::BangEqual => Some(ASTBinaryOperatorKind::NotEqual),
+            TokenKind::Ampersand => Some(ASTBinaryOperatorKind::BitwiseAnd),
+            TokenKind::Caret => Some(ASTBinaryOperatorKind::BitwiseXor),
+            TokenKind::Pipe => Some(ASTBinaryOperatorKind::BitwiseOr),
+            TokenKind::AmpersandAmpersand => Some(ASTBinaryOperatorKind::LogicalAnd),
+            TokenKind::PipePipe => Some(ASTBinaryOperatorKind::LogicalOr),
             TokenKind::Equal => Some(ASTBinaryOperatorKind::Equal),
+            TokenKind::Comma => Some(ASTBinaryOperatorKind::Comma),
+            ref kind if Self::compound_assignment_kind(kind).is_some() => Some(ASTBinaryOperatorKind::Equal),
             _ => {None}
         };
         kind.map(|kind|ASTBinaryOperator::new(kind,token.clone()))
         
     }
 
+    /// The operator a compound assignment token such as `+=` applies before storing.
+    fn compound_assignment_kind(kind:&TokenKind) -> Option<ASTBinaryOperatorKind>{
+        match kind {
+            TokenKind::PlusEqual => Some(ASTBinaryOperatorKind::Add),
+            TokenKind::MinusEqual => Some(ASTBinaryOperatorKind::Sub),
+            TokenKind::AsteriskEqual => Some(ASTBinaryOperatorKind::Mul),
+            TokenKind::SlashEqual => Some(ASTBinaryOperatorKind::Div),
+            TokenKind::PercentEqual => Some(ASTBinaryOperatorKind::Mod),
+            TokenKind::LessLessEqual => Some(ASTBinaryOperatorKind::ShiftLeft),
+            TokenKind::GreaterGreaterEqual => Some(ASTBinaryOperatorKind::ShiftRight),
+            TokenKind::AmpersandEqual => Some(ASTBinaryOperatorKind::BitwiseAnd),
+            TokenKind::PipeEqual => Some(ASTBinaryOperatorKind::BitwiseOr),
+            TokenKind::CaretEqual => Some(ASTBinaryOperatorKind::BitwiseXor),
+            _ => None,
+        }
+    }
+
+    fn is_assignable(expr:&ASTExpression) -> bool{
+        LeftValue::is_left_value(expr) && !expr.ty.is_array() && !matches!(expr.ty,GrammarVartype::Function(_))
+    }
+
+    /// Reports storing `expr` in an object of type `target` with `message`, unless it converts to it.
+    fn check_assignable(&mut self,target:&GrammarVartype,expr:&ASTExpression,message:String,span:&TextSpan) -> bool{
+        if matches!(expr.kind,ASTExpressionKind::Error(_)) || target.is_assignable_from(expr) {
+            return true;
+        }
+        self.diagnostics_bag.borrow_mut().report_error(message,span.clone());
+        false
+    }
+    /// Reports applying `operator` to operands of types it does not take, unless one already had an error.
+    fn report_invalid_operands(&mut self,operator:&ASTBinaryOperator,left:&ASTExpression,right:&ASTExpression) -> ASTExpression{
+        for operand in [left,right] {
+            if let ASTExpressionKind::Error(_) = operand.kind {
+                return operand.clone();
+            }
+        }
+        let span = operator.token().span.clone();
+        let message = format!("Invalid operands to binary expression: {} and {}",left.ty,right.ty);
+        self.diagnostics_bag.borrow_mut().report_error(message,span.clone());
+        ASTExpression::error(span)
+    }
+    /// Reports assigning to `operand` through `operator`, unless `operand` already had an error.
+    fn report_not_assignable(&mut self,operator:&Token,operand:ASTExpression) -> ASTExpression{
+        if let ASTExpressionKind::Error(_) = operand.kind {
+            return operand;
+        }
+        let span = operator.span.clone();
+        self.diagnostics_bag.borrow_mut().report_error("Expression is not assignable".to_string(),span.clone());
+        ASTExpression::error(span)
+    }
+
+    /// Builds `condition ? then_expr : else_expr`, reporting a condition that is not a scalar
+    /// and operands that have no common type.
+    fn check_conditional(&mut self,condition:ASTExpression,then_expr:ASTExpression,else_expr:ASTExpression,question:&Token) -> ASTExpression{
+        let operands = [&condition,&then_expr,&else_expr];
+        if operands.iter().any(|operand| matches!(operand.kind,ASTExpressionKind::Error(_))) {
+            return ASTExpression::conditional(condition,then_expr,else_expr);
+        }
+        let (then_ty,else_ty) = (then_expr.ty.decay(),else_expr.ty.decay());
+        let compatible = (then_ty.is_arithmetic() && else_ty.is_arithmetic())
+            || (then_ty.is_record() && then_ty == else_ty)
+            || (then_ty.is_pointer() && (else_ty.is_pointer() || else_expr.is_null_pointer_constant()))
+            || (else_ty.is_pointer() && then_expr.is_null_pointer_constant());
+        let message = if !condition.ty.is_scalar() {
+            format!("Used type {} where arithmetic or pointer type is required",condition.ty)
+        } else if !compatible {
+            format!("Incompatible operand types: {} and {}",then_expr.ty,else_expr.ty)
+        } else {
+            return ASTExpression::conditional(condition,then_expr,else_expr);
+        };
+        self.diagnostics_bag.borrow_mut().report_error(message,question.span.clone());
+        ASTExpression::error(question.span.clone())
+    }
+
+    fn parse_increment_kind(&mut self) -> Option<ASTIncrementKind>{
+        match self.current().kind {
+            TokenKind::PlusPlus => Some(ASTIncrementKind::Increment),
+            TokenKind::MinusMinus => Some(ASTIncrementKind::Decrement),
+            _ => None,
+        }
+    }
+
     fn parse_binary_expression(&mut self,precedence:u8) -> ASTExpression {
-        let mut left = self.parse_primary_expression();
-        while let Some(operator) = self.parse_binary_operator() {
+        let mut left = self.parse_unary_expression();
+        loop {
+            if self.current().kind == TokenKind::Question {
+                // `?:` groups to the right, so an equal precedence keeps going.
+                if CONDITIONAL_PRECEDENCE < precedence {
+                    break;
+                }
+                let question = self.consume().unwrap().clone();
+                let then_expr = self.parse_expression();
+                self.consume_and_check(TokenKind::Colon);
+                let else_expr = self.parse_binary_expression(CONDITIONAL_PRECEDENCE);
+                left = self.check_conditional(left,then_expr,else_expr,&question);
+                continue;
+            }
+            let Some(operator) = self.parse_binary_operator() else {
+                break;
+            };
             let operator_precedence = operator.precedence();
             if operator_precedence < precedence || (operator_precedence == precedence && !operator.right_combined()){
                 break;
@@ -240,7 +1339,33 @@ impl Parser {
             let right = self.parse_binary_expression(operator_precedence);
             match operator.kind { 
                 ASTBinaryOperatorKind::Equal => {
-                    left = ASTExpression::assignment(left,right)
+                    if !Self::is_assignable(&left) {
+                        left = self.report_not_assignable(operator.token(),left);
+                        continue;
+                    }
+                    left = match Self::compound_assignment_kind(&operator.token().kind) {
+                        Some(kind) => {
+                            let compound = ASTBinaryOperator::new(kind,operator.token().clone());
+                            // The result is stored back, so a pointer only takes an integer.
+                            let fits = if left.ty.is_pointer() { right.ty.is_integer() } else { right.ty.decay().is_arithmetic() };
+                            if !compound.accepts(&left,&right) || !fits {
+                                left = self.report_invalid_operands(&compound,&left,&right);
+                                continue;
+                            }
+                            ASTExpression::compound_assignment(left,compound,right)
+                        }
+                        None => {
+                            let message = format!("Assigning to {} from incompatible type {}",left.ty,right.ty);
+                            if !self.check_assignable(&left.ty,&right,message,&operator.token().span) {
+                                left = ASTExpression::error(operator.token().span.clone());
+                                continue;
+                            }
+                            ASTExpression::assignment(left,right)
+                        }
+                    }
+                }
+                _ if !operator.accepts(&left,&right) => {
+                    left = self.report_invalid_operands(&operator,&left,&right);
                 }
                 _ => {
                     left = ASTExpression::binary(operator,left,right)
@@ -249,46 +1374,301 @@ impl Parser {
         }
         left
     }
+    fn parse_unary_operator(&mut self) -> Option<ASTUnaryOperator>{
+        let token = self.current();
+        let kind = match token.kind {
+            TokenKind::Minus => Some(ASTUnaryOperatorKind::Negate),
+            TokenKind::Plus => Some(ASTUnaryOperatorKind::Plus),
+            TokenKind::Bang => Some(ASTUnaryOperatorKind::LogicalNot),
+            TokenKind::Tilde => Some(ASTUnaryOperatorKind::BitwiseNot),
+            TokenKind::Ampersand => Some(ASTUnaryOperatorKind::AddressOf),
+            TokenKind::Asterisk => Some(ASTUnaryOperatorKind::Dereference),
+            _ => None,
+        };
+        kind.map(|kind|ASTUnaryOperator::new(kind,token.clone()))
+    }
+
+    /// Parses prefix operators, which bind tighter than every binary operator.
+    fn parse_unary_expression(&mut self) -> ASTExpression{
+        if let Some(kind) = self.parse_increment_kind() {
+            let operator = self.current().clone();
+            self.consume();
+            let operand = self.parse_unary_expression();
+            if !Self::is_assignable(&operand) {
+                return self.report_not_assignable(&operator,operand);
+            }
+            return ASTExpression::increment(operand,kind,false);
+        }
+        if self.current().kind == TokenKind::Sizeof {
+            return self.parse_sizeof();
+        }
+        if self.current().kind == TokenKind::LeftParen && self.is_type_start(&self.peek(1).kind) {
+            return self.parse_cast();
+        }
+        let Some(operator) = self.parse_unary_operator() else {
+            return self.parse_postfix_expression();
+        };
+        self.consume();
+        let operand = self.parse_unary_expression();
+        if let ASTExpressionKind::Error(_) = operand.kind {
+            return operand;
+        }
+        let message = match operator.kind {
+            ASTUnaryOperatorKind::AddressOf if !LeftValue::is_left_value(&operand) => "Cannot take the address of an rvalue",
+            ASTUnaryOperatorKind::Dereference if !operand.ty.decay().is_pointer() => "Indirection requires pointer operand",
+            ASTUnaryOperatorKind::Negate | ASTUnaryOperatorKind::Plus if !operand.ty.is_arithmetic() => "Invalid argument type to unary expression",
+            ASTUnaryOperatorKind::BitwiseNot if !operand.ty.is_integer() => "Invalid argument type to unary expression",
+            ASTUnaryOperatorKind::LogicalNot if !operand.ty.is_scalar() => "Invalid argument type to unary expression",
+            _ => return ASTExpression::unary(operator,operand),
+        };
+        let span = operator.token().span.clone();
+        self.diagnostics_bag.borrow_mut().report_error(format!("{}: {}",message,operand.ty),span.clone());
+        ASTExpression::error(span)
+    }
+    /// Parses `sizeof expr` or `sizeof(type)`, folded to the size in bytes.
+    fn parse_sizeof(&mut self) -> ASTExpression{
+        self.consume();
+        let ty = if self.current().kind == TokenKind::LeftParen && self.is_type_start(&self.peek(1).kind) {
+            self.consume();
+            let ty = self.parse_type_name();
+            self.consume_and_check(TokenKind::RightParen);
+            ty
+        } else {
+            // The operand is not evaluated, only its type matters.
+            self.parse_unary_expression().ty
+        };
+        ASTExpression::typed_number(ty.size() as i64,GrammarVartype::Direct(VartypeKind::UnsignedLong))
+    }
+    /// Parses `(type) operand`. Only scalar values convert to scalar types.
+    fn parse_cast(&mut self) -> ASTExpression{
+        let paren = self.consume().unwrap().clone();
+        let ty = self.parse_type_name();
+        self.consume_and_check(TokenKind::RightParen);
+        let operand = self.parse_unary_expression();
+        if let ASTExpressionKind::Error(_) = operand.kind {
+            return operand;
+        }
+        let message = if ty.is_aggregate() || matches!(ty,GrammarVartype::Function(_)) {
+            format!("Used type {} where arithmetic or pointer type is required",ty)
+        } else if operand.ty.is_record() {
+            format!("Operand of type {} where arithmetic or pointer type is required",operand.ty)
+        } else if ty.is_pointer() && operand.ty.is_floating() {
+            format!("Operand of type {} cannot be cast to a pointer type",operand.ty)
+        } else if ty.is_floating() && operand.ty.decay().is_pointer() {
+            format!("Pointer cannot be cast to type {}",ty)
+        } else {
+            return ASTExpression::cast(ty,operand);
+        };
+        self.diagnostics_bag.borrow_mut().report_error(message,paren.span.clone());
+        ASTExpression::error(paren.span)
+    }
+    /// Parses a type without a declared name, as `int *[4]`.
+    fn parse_type_name(&mut self) -> GrammarVartype{
+        let base = self.parse_vartype();
+        let declarator = self.parse_declarator(&base,false);
+        if let Some(token) = &declarator.name_token {
+            self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::RightParen,token);
+        }
+        declarator.ty
+    }
+    /// Parses `[index]` as `*(expr + index)`, which also makes it an lvalue.
+    fn parse_subscript(&mut self,expr:ASTExpression) -> ASTExpression{
+        let bracket = self.consume().unwrap().clone();
+        let index = self.parse_expression();
+        self.consume_and_check(TokenKind::RightBracket);
+        if let ASTExpressionKind::Error(_) = expr.kind {
+            return expr;
+        }
+        if !expr.ty.decay().is_pointer() && !index.ty.decay().is_pointer() {
+            self.diagnostics_bag.borrow_mut().report_error(format!("Subscripted value is not an array or pointer: {}",expr.ty),bracket.span.clone());
+            return ASTExpression::error(bracket.span);
+        }
+        let add = ASTBinaryOperator::new(ASTBinaryOperatorKind::Add,bracket.clone());
+        if !add.accepts(&expr,&index) {
+            self.diagnostics_bag.borrow_mut().report_error("Array subscript is not an integer".to_string(),bracket.span.clone());
+            return ASTExpression::error(bracket.span);
+        }
+        let deref = ASTUnaryOperator::new(ASTUnaryOperatorKind::Dereference,bracket);
+        ASTExpression::unary(deref,ASTExpression::binary(add,expr,index))
+    }
+    /// Parses `.name` or `->name` after `expr`.
+    fn parse_member_access(&mut self,expr:ASTExpression) -> ASTExpression{
+        let operator = self.consume().unwrap().clone();
+        let arrow = operator.kind == TokenKind::Arrow;
+        let name_token = self.current().clone();
+        let name = self.parse_identifier();
+        if let ASTExpressionKind::Error(_) = expr.kind {
+            return expr;
+        }
+        let ty = expr.ty.decay();
+        let record = match (&ty,arrow) {
+            (GrammarVartype::Record(record),false) => Some(record.clone()),
+            (GrammarVartype::Ref(pointee),true) => match pointee.as_ref() {
+                GrammarVartype::Record(record) => Some(record.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let message = match &record {
+            None if arrow && !ty.is_pointer() => format!("Member reference type is not a pointer: {}",expr.ty),
+            None => format!("Member reference base type is not a structure or union: {}",expr.ty),
+            Some(record) if !record.is_complete() => format!("Member access into incomplete type: {}",record),
+            Some(record) => match record.member(&name) {
+                Some(member) => return ASTExpression::member(expr,member,operator),
+                None => format!("No member named {} in {}",name,record),
+            },
+        };
+        self.diagnostics_bag.borrow_mut().report_error(message,name_token.span.clone());
+        ASTExpression::error(name_token.span)
+    }
+    /// Parses postfix operators, which bind tighter than the prefix ones.
+    fn parse_postfix_expression(&mut self) -> ASTExpression{
+        let mut expr = self.parse_primary_expression();
+        loop {
+            if self.current().kind == TokenKind::LeftBracket {
+                expr = self.parse_subscript(expr);
+                continue;
+            }
+            if matches!(self.current().kind, TokenKind::Dot | TokenKind::Arrow) {
+                expr = self.parse_member_access(expr);
+                continue;
+            }
+            if self.current().kind == TokenKind::LeftParen {
+                expr = self.parse_call(expr);
+                continue;
+            }
+            let Some(kind) = self.parse_increment_kind() else {
+                break;
+            };
+            let operator = self.current().clone();
+            self.consume();
+            if !Self::is_assignable(&expr) {
+                expr = self.report_not_assignable(&operator,expr);
+                continue;
+            }
+            expr = ASTExpression::increment(expr,kind,true);
+        }
+        expr
+    }
+    /// Parses `(arguments)` calling `callee`, a function or a pointer to one.
+    fn parse_call(&mut self,callee:ASTExpression) -> ASTExpression{
+        let paren = self.consume().unwrap().clone();
+        let mut param_list = vec![];
+        if self.current().kind != TokenKind::RightParen {
+            loop {
+                param_list.push(self.parse_assignment_expression());
+                if self.current().kind != TokenKind::Comma {
+                    break;
+                }
+                self.consume();
+            }
+        }
+        if self.current().kind != TokenKind::RightParen {
+            self.diagnostics_bag.borrow_mut().report_unexpected_token(&TokenKind::Comma,self.current());
+            self.skip_to_closing_paren();
+            return ASTExpression::error(paren.span);
+        }
+        self.consume();
+        if let ASTExpressionKind::Error(_) = callee.kind {
+            return callee;
+        }
+        let function = match callee.ty.decay() {
+            GrammarVartype::Ref(pointee) => match *pointee {
+                GrammarVartype::Function(function) => Some(function),
+                _ => None,
+            },
+            _ => None,
+        };
+        let message = match function {
+            None => format!("Called object type is not a function or function pointer: {}",callee.ty),
+            // An empty parameter list leaves the parameters unspecified.
+            Some(function) if !function.arguments.is_empty() && function.arguments.len() != param_list.len() => {
+                let quantity = if param_list.len() < function.arguments.len() { "few" } else { "many" };
+                format!("Too {} arguments to function call, expected {}, have {}",quantity,function.arguments.len(),param_list.len())
+            }
+            Some(function) => {
+                for (index,(argument,parameter)) in param_list.iter().zip(&function.arguments).enumerate() {
+                    let message = format!("Passing {} to parameter {} of incompatible type {}",argument.ty,index+1,parameter);
+                    if !self.check_assignable(parameter,argument,message,&paren.span) {
+                        return ASTExpression::error(paren.span);
+                    }
+                }
+                return ASTExpression::function_call(callee,param_list,paren,function.returntype);
+            }
+        };
+        self.diagnostics_bag.borrow_mut().report_error(message,paren.span.clone());
+        ASTExpression::error(paren.span)
+    }
+    /// Skips the rest of a malformed argument list, up to its `)` or the end of the statement.
+    fn skip_to_closing_paren(&mut self){
+        let mut depth = 0;
+        loop {
+            match self.current().kind {
+                TokenKind::SemiColon | TokenKind::Eof => break,
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen if depth == 0 => {
+                    self.consume();
+                    break;
+                }
+                TokenKind::RightParen => depth -= 1,
+                _ => {}
+            }
+            self.consume();
+        }
+    }
     fn parse_primary_expression(&mut self) -> ASTExpression{
         let token = self.current();
         match &token.kind {
-            TokenKind::Number(number) => {
+            TokenKind::Number(_) => {
+                let token = token.clone();
+                self.consume();
+                self.parse_integer_literal(&token)
+            }
+            TokenKind::CharLiteral(value) => {
+                let value = *value;
                 self.consume();
-                ASTExpression::number(*number)
+                ASTExpression::number(value)
+            }
+            TokenKind::StringLiteral(_) => {
+                // Adjacent string literals make up a single one.
+                let mut value = Vec::new();
+                while let TokenKind::StringLiteral(bytes) = &self.current().kind {
+                    value.extend_from_slice(bytes);
+                    self.consume();
+                }
+                ASTExpression::string(value)
+            }
+            TokenKind::FloatNumber(value) => {
+                let (token,value) = (token.clone(),*value);
+                self.consume();
+                self.parse_floating_literal(&token,value)
             }
             TokenKind::LeftParen => {
                 self.consume();
                 let expr = self.parse_expression();
-                self.consume();
+                self.consume_and_check(TokenKind::RightParen);
                 expr
             }
             TokenKind::Identifier(name) =>{
                 let name = name.clone();
+                let name_token = token.clone();
                 self.consume();
-                let token1 = self.current();
-                if let TokenKind::LeftParen = token1.kind{
-                    self.consume();
-                    let mut param_list = vec![];
-                    loop {
-                        let token = self.current();
-                        match token.kind { 
-                            TokenKind::RightParen => {
-                                self.consume();
-                                break;
-                            }
-                            TokenKind::Comma => {
-                                self.consume();
-                            }
-                            _ => {
-                                let expr = self.parse_expression();
-                                param_list.push(Box::new(expr));
-                            }
+                match self.symbols.lookup(&name) {
+                    Some(Symbol::Variable(ty)) => {
+                        if let GrammarVartype::Function(_) = ty {
+                            self.function_uses.push(name_token);
                         }
+                        ASTExpression::variable(name,ty.clone())
+                    }
+                    Some(Symbol::Enumerator(value)) => ASTExpression::number(*value),
+                    Some(Symbol::Typedef(_)) => {
+                        self.diagnostics_bag.borrow_mut().report_error(format!("Unexpected type name {}: expected expression",name),name_token.span.clone());
+                        ASTExpression::error(name_token.span)
+                    }
+                    None => {
+                        self.diagnostics_bag.borrow_mut().report_error(format!("Use of undeclared identifier: {}",name),name_token.span.clone());
+                        ASTExpression::error(name_token.span)
                     }
-                    ASTExpression::function_call(name,param_list)
-                }
-                else {
-                    ASTExpression::variable(name.clone())
                 }
             }
             _ => {
@@ -313,7 +1693,7 @@ impl Parser {
     fn consume(&self) -> Option<&Token>{
         self.current.increment();
         let token = self.peek(-1);
-        return Some(token);
+        Some(token)
     }
     fn consume_and_check(&mut self,kind:TokenKind) -> &Token{
 
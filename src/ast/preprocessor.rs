@@ -0,0 +1,732 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::ast::lexer::{Lexer, TextSpan, Token, TokenKind};
+use crate::ast::parser::Parser;
+use crate::diagnostics::{DiagnosticBag, DiagnosticsBagCell};
+use crate::text::SourceMap;
+
+/// The names of the macros a token was produced by, which it may no longer invoke.
+type HideSet = Vec<String>;
+type HiddenToken = (Token,HideSet);
+
+const MAX_INCLUDE_DEPTH: usize = 200;
+const COMMAND_LINE_FILE: &str = "<command line>";
+
+/// A `-D` or `-U` option, applied in command-line order before the main file.
+#[derive(Debug,Clone)]
+pub enum MacroOption {
+    Define(String,String),
+    Undefine(String),
+}
+
+impl MacroOption {
+    /// Parses the argument of `-D`: `NAME` defines it as `1`, `NAME=VALUE` as `VALUE`.
+    pub fn define(argument:&str) -> Self {
+        match argument.split_once('=') {
+            Some((name,value)) => MacroOption::Define(name.to_string(),value.to_string()),
+            None => MacroOption::Define(argument.to_string(),"1".to_string()),
+        }
+    }
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct PreprocessorOptions {
+    pub include_paths: Vec<PathBuf>,
+    pub macro_options: Vec<MacroOption>,
+}
+
+#[derive(Debug,Clone)]
+struct Macro {
+    params: Option<Vec<String>>,
+    body: Vec<Token>,
+}
+
+impl Macro {
+    fn param_index(&self,token:&Token) -> Option<usize> {
+        let name = identifier_name(token)?;
+        self.params.as_ref()?.iter().position(|param| param == name)
+    }
+    fn same_definition(&self,other:&Macro) -> bool {
+        self.params == other.params
+            && self.body.len() == other.body.len()
+            && self.body.iter().zip(&other.body).all(|(a,b)| a.span.literal == b.span.literal)
+    }
+}
+
+/// An open `#if`, `#ifdef` or `#ifndef` group.
+#[derive(Debug)]
+struct Conditional {
+    /// Whether the lines of the current branch are kept.
+    active: bool,
+    /// Whether one of the branches was already kept.
+    taken: bool,
+    seen_else: bool,
+    span: TextSpan,
+}
+
+/// Expands `#include`, `#define` and conditional directives on the token stream of
+/// the `Lexer` before it is handed to the `Parser`.
+pub struct Preprocessor {
+    sources: SourceMap,
+    include_paths: Vec<PathBuf>,
+    macros: HashMap<String,Macro>,
+    include_depth: usize,
+    diagnostics_bag: DiagnosticsBagCell,
+}
+
+/// Returns the name of an identifier-like token, keywords included.
+fn identifier_name(token:&Token) -> Option<&str> {
+    let literal = token.span.literal.as_str();
+    let first = literal.chars().next()?;
+    if first.is_alphabetic() || first == '_' {
+        Some(literal)
+    } else {
+        None
+    }
+}
+
+/// Tells whether `next` was written right after `previous` without any whitespace.
+fn is_adjacent(previous:&Token,next:&Token) -> bool {
+    previous.span.file == next.span.file && previous.span.end == next.span.start
+}
+
+/// Spells the tokens back as text, separating them by a space where the source had whitespace.
+pub fn spell_tokens(tokens:&[Token]) -> String {
+    let mut text = String::new();
+    for (index,token) in tokens.iter().enumerate() {
+        if index > 0 && !is_adjacent(&tokens[index-1],token) {
+            text.push(' ');
+        }
+        text.push_str(&token.span.literal);
+    }
+    text
+}
+
+/// Prints the preprocessed tokens back as source text, as `cc -E` does, with
+/// `# <line> "<file>"` markers wherever the text does not follow on from the previous line.
+pub fn print_preprocessed(tokens:&[Token],sources:&SourceMap) -> String {
+    const MAX_BLANK_LINES: usize = 8;
+    let mut text = String::new();
+    let mut position: Option<(usize,usize)> = None;
+    let mut previous: Option<&Token> = None;
+    for token in tokens.iter().filter(|token| token.kind != TokenKind::Eof) {
+        let file = token.span.file;
+        let line = sources.source(file).line_index(token.span.start)+1;
+        match position {
+            Some((current_file,current_line)) if current_file == file && current_line == line => {
+                if previous.is_some_and(|previous| !is_adjacent(previous,token)) {
+                    text.push(' ');
+                }
+            }
+            Some((current_file,current_line)) if current_file == file && line > current_line && line-current_line <= MAX_BLANK_LINES => {
+                text.push_str(&"\n".repeat(line-current_line));
+            }
+            _ => {
+                if position.is_some() {
+                    text.push('\n');
+                }
+                text.push_str(&format!("# {} \"{}\"\n",line,sources.path(file)));
+            }
+        }
+        text.push_str(&token.span.literal);
+        position = Some((file,line));
+        previous = Some(token);
+    }
+    if position.is_some() {
+        text.push('\n');
+    }
+    text
+}
+
+fn is_newline(token:&Token) -> bool {
+    token.kind == TokenKind::WhiteSpace && token.span.literal.contains('\n')
+}
+
+/// Splits a token stream into logical lines without whitespace, joining lines ending with `\`.
+fn split_lines(tokens:Vec<Token>) -> Vec<Vec<Token>> {
+    let mut lines = vec![];
+    let mut line = vec![];
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token.kind {
+            TokenKind::Eof => break,
+            TokenKind::Bad if token.span.literal == "\\" && tokens.peek().is_some_and(is_newline) => {
+                tokens.next();
+            }
+            _ if token.kind.is_trivia() => {
+                if is_newline(&token) {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+            _ => line.push(token),
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+impl Preprocessor {
+    pub fn new(options:&PreprocessorOptions,diagnostics_bag:DiagnosticsBagCell) -> Self {
+        let mut preprocessor = Self {
+            sources: SourceMap::new(),
+            include_paths: options.include_paths.clone(),
+            macros: HashMap::new(),
+            include_depth: 0,
+            diagnostics_bag,
+        };
+        if !options.macro_options.is_empty() {
+            let command_line: String = options.macro_options.iter().map(|option| match option {
+                MacroOption::Define(name,value) => format!("#define {} {}\n",name,value),
+                MacroOption::Undefine(name) => format!("#undef {}\n",name),
+            }).collect();
+            preprocessor.preprocess_file(COMMAND_LINE_FILE.to_string(),command_line);
+        }
+        preprocessor
+    }
+
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    pub fn into_sources(self) -> SourceMap {
+        self.sources
+    }
+
+    /// Preprocesses the main file, returning the tokens for the `Parser` ending with `Eof`.
+    pub fn preprocess(&mut self,path:&str,text:String) -> Vec<Token> {
+        let end = text.len();
+        let file = self.sources.len();
+        let mut tokens = self.preprocess_file(path.to_string(),text);
+        tokens.push(Token::new(TokenKind::Eof,TextSpan::new(end,end,'\0'.to_string()).in_file(file)));
+        tokens
+    }
+
+    fn preprocess_file(&mut self,path:String,text:String) -> Vec<Token> {
+        let file = self.sources.add_file(path,text);
+        let first_diagnostic = self.diagnostics_bag.borrow().diagnostics.len();
+        let lexer_diagnostics: DiagnosticsBagCell = Rc::new(RefCell::new(DiagnosticBag::new()));
+        let tokens: Vec<Token> = {
+            let mut lexer = Lexer::for_file(self.sources.source(file).text(),file).reporting_to(lexer_diagnostics.clone());
+            std::iter::from_fn(|| lexer.next_token()).collect()
+        };
+        let literals: Vec<_> = tokens.iter()
+            .filter(|token| matches!(token.kind,TokenKind::CharLiteral(_) | TokenKind::StringLiteral(_)))
+            .map(|token| token.span.start..token.span.end)
+            .collect();
+        let mut output = vec![];
+        let mut pending = vec![];
+        let mut conditionals = vec![];
+        let mut skipped = vec![];
+        let mut skip_start = None;
+        for line in split_lines(tokens) {
+            if line.first().is_some_and(|token| token.kind == TokenKind::Hash) {
+                output.extend(self.expand(&pending));
+                pending.clear();
+                self.process_directive(&line,&mut conditionals,&mut output);
+                match (skip_start,Self::is_active(&conditionals)) {
+                    (None,false) => skip_start = Some(line[line.len()-1].span.end),
+                    (Some(start),true) => {
+                        skipped.push(start..line[0].span.start);
+                        skip_start = None;
+                    }
+                    _ => {}
+                }
+            } else if Self::is_active(&conditionals) {
+                pending.extend(line);
+            }
+        }
+        output.extend(self.expand(&pending));
+        for conditional in conditionals {
+            self.report_error("Unterminated conditional directive".to_string(),&conditional.span);
+        }
+        // Literals in skipped groups are never converted, so their malformed contents and escapes
+        // are not errors; the rest goes before the diagnostics of the file.
+        skipped.extend(skip_start.map(|start| start..usize::MAX));
+        let in_skipped_literal = |position:&usize| {
+            skipped.iter().any(|range| range.contains(position)) && literals.iter().any(|range| range.contains(position))
+        };
+        let diagnostics = std::mem::take(&mut lexer_diagnostics.borrow_mut().diagnostics);
+        let diagnostics = diagnostics.into_iter().filter(|diagnostic| !in_skipped_literal(&diagnostic.span.start));
+        self.diagnostics_bag.borrow_mut().diagnostics.splice(first_diagnostic..first_diagnostic,diagnostics);
+        output
+    }
+
+    fn is_active(conditionals:&[Conditional]) -> bool {
+        conditionals.iter().all(|conditional| conditional.active)
+    }
+
+    fn process_directive(&mut self,line:&[Token],conditionals:&mut Vec<Conditional>,output:&mut Vec<Token>) {
+        let Some(directive) = line.get(1) else {
+            return;
+        };
+        let name = directive.span.literal.as_str();
+        if matches!(name,"if" | "ifdef" | "ifndef" | "elif" | "else" | "endif") {
+            self.process_conditional(line,conditionals);
+            return;
+        }
+        if !Self::is_active(conditionals) {
+            return;
+        }
+        match name {
+            "define" => self.process_define(line),
+            "undef" => self.process_undef(line),
+            "include" => self.process_include(line,output),
+            "error" => self.report_error(format!("#error {}",spell_tokens(&line[2..])),&directive.span),
+            "pragma" => {}
+            _ => self.report_error(
+                format!("Invalid preprocessing directive: #{}",directive.span.literal),
+                &directive.span,
+            ),
+        }
+    }
+
+    fn process_conditional(&mut self,line:&[Token],conditionals:&mut Vec<Conditional>) {
+        let directive = &line[1];
+        let span = directive.span.clone();
+        match directive.span.literal.as_str() {
+            "if" | "ifdef" | "ifndef" => {
+                let active = Self::is_active(conditionals) && self.evaluate_condition(line);
+                conditionals.push(Conditional { active, taken: active, seen_else: false, span });
+            }
+            "elif" | "else" => {
+                let parent_active = conditionals.len() < 2 || Self::is_active(&conditionals[..conditionals.len()-1]);
+                let Some(conditional) = conditionals.last() else {
+                    self.report_error(format!("#{} without #if",directive.span.literal),&span);
+                    return;
+                };
+                if conditional.seen_else {
+                    self.report_error(format!("#{} after #else",directive.span.literal),&span);
+                    return;
+                }
+                let taken = conditional.taken;
+                let active = parent_active && !taken && self.evaluate_condition(line);
+                let conditional = conditionals.last_mut().unwrap();
+                conditional.active = active;
+                conditional.taken = taken || active;
+                conditional.seen_else = directive.span.literal == "else";
+                conditional.span = span;
+            }
+            _ => {
+                if conditionals.pop().is_none() {
+                    self.report_error("#endif without #if".to_string(),&span);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the condition of `#if`, `#elif`, `#ifdef` or `#ifndef`; `#else` is always true.
+    fn evaluate_condition(&mut self,line:&[Token]) -> bool {
+        let directive = &line[1];
+        match directive.span.literal.as_str() {
+            "ifdef" | "ifndef" => {
+                let Some(name) = line.get(2).and_then(identifier_name) else {
+                    self.report_error("Macro name missing".to_string(),&directive.span);
+                    return false;
+                };
+                self.macros.contains_key(name) == (directive.span.literal == "ifdef")
+            }
+            "else" => true,
+            _ => {
+                if line.len() < 3 {
+                    self.report_error(format!("#{} with no expression",directive.span.literal),&directive.span);
+                    return false;
+                }
+                self.evaluate_expression(line)
+            }
+        }
+    }
+
+    /// Evaluates the integer constant expression after `#if` or `#elif`.
+    fn evaluate_expression(&mut self,line:&[Token]) -> bool {
+        let directive = &line[1];
+        let mut tokens = vec![];
+        let mut index = 2;
+        while index < line.len() {
+            let token = &line[index];
+            index += 1;
+            if identifier_name(token) != Some("defined") {
+                tokens.push(token.clone());
+                continue;
+            }
+            let parenthesized = line.get(index).is_some_and(|token| token.kind == TokenKind::LeftParen);
+            let name_index = if parenthesized { index+1 } else { index };
+            let Some(name) = line.get(name_index).and_then(identifier_name) else {
+                self.report_error("Operator 'defined' requires an identifier".to_string(),&token.span);
+                return false;
+            };
+            index = name_index+1;
+            if parenthesized {
+                if line.get(index).map(|token| &token.kind) != Some(&TokenKind::RightParen) {
+                    self.report_error("Missing ')' after 'defined'".to_string(),&token.span);
+                    return false;
+                }
+                index += 1;
+            }
+            let value = self.macros.contains_key(name) as i64;
+            let span = TextSpan::new(token.span.start,token.span.end,value.to_string()).in_file(token.span.file);
+            tokens.push(Token::new(TokenKind::Number(value),span));
+        }
+        let mut tokens: Vec<Token> = self.expand(&tokens).into_iter().map(|token| {
+            match identifier_name(&token) {
+                Some(_) => {
+                    let span = TextSpan::new(token.span.start,token.span.end,"0".to_string()).in_file(token.span.file);
+                    Token::new(TokenKind::Number(0),span)
+                }
+                None => token,
+            }
+        }).collect();
+        let end = line.last().unwrap().span.clone();
+        tokens.push(Token::new(TokenKind::Eof,TextSpan::new(end.end,end.end,String::new()).in_file(end.file)));
+        let errors = self.diagnostics_bag.borrow().diagnostics.len();
+        let expr = Parser::new(tokens,self.diagnostics_bag.clone()).parse_constant_expression();
+        if self.diagnostics_bag.borrow().diagnostics.len() > errors {
+            return false;
+        }
+        match expr.constant_value() {
+            Some(value) => value != 0,
+            None => {
+                self.report_error(format!("Invalid integer constant expression in #{}",directive.span.literal),&directive.span);
+                false
+            }
+        }
+    }
+
+    fn process_define(&mut self,line:&[Token]) {
+        let Some(name_token) = line.get(2) else {
+            self.report_error("Macro name missing".to_string(),&line[1].span);
+            return;
+        };
+        let Some(name) = identifier_name(name_token) else {
+            self.report_error("Macro names must be identifiers".to_string(),&name_token.span);
+            return;
+        };
+        let mut body_start = 3;
+        let mut params = None;
+        if line.get(3).is_some_and(|token| token.kind == TokenKind::LeftParen && is_adjacent(name_token,token)) {
+            match self.parse_macro_params(line) {
+                Some((names,end)) => {
+                    params = Some(names);
+                    body_start = end;
+                }
+                None => return,
+            }
+        }
+        let definition = Macro { params, body: line[body_start..].to_vec() };
+        if !self.check_macro_body(&definition) {
+            return;
+        }
+        if let Some(previous) = self.macros.get(name) {
+            if !previous.same_definition(&definition) {
+                self.diagnostics_bag.borrow_mut().report_warning(
+                    format!("Macro redefined: {}",name),
+                    name_token.span.clone(),
+                );
+            }
+        }
+        self.macros.insert(name.to_string(),definition);
+    }
+
+    /// Reads the parameter list starting at `line[3]`, returning the names and the index after `)`.
+    fn parse_macro_params(&mut self,line:&[Token]) -> Option<(Vec<String>,usize)> {
+        let mut names: Vec<String> = vec![];
+        let mut index = 4;
+        if line.get(index).is_some_and(|token| token.kind == TokenKind::RightParen) {
+            return Some((names,index+1));
+        }
+        loop {
+            let Some(token) = line.get(index) else {
+                self.report_error("Missing ')' in macro parameter list".to_string(),&line[index-1].span);
+                return None;
+            };
+            let Some(name) = identifier_name(token) else {
+                self.report_error(format!("Invalid macro parameter: {}",token.span.literal),&token.span);
+                return None;
+            };
+            if names.iter().any(|param| param == name) {
+                self.report_error(format!("Duplicate macro parameter: {}",name),&token.span);
+                return None;
+            }
+            names.push(name.to_string());
+            index += 1;
+            match line.get(index).map(|token| &token.kind) {
+                Some(TokenKind::Comma) => index += 1,
+                Some(TokenKind::RightParen) => return Some((names,index+1)),
+                _ => {
+                    self.report_error("Expected ',' or ')' in macro parameter list".to_string(),&line[index-1].span);
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn check_macro_body(&mut self,definition:&Macro) -> bool {
+        let body = &definition.body;
+        for (index,token) in body.iter().enumerate() {
+            match token.kind {
+                TokenKind::HashHash if index == 0 || index == body.len()-1 => {
+                    self.report_error("'##' cannot appear at either end of a macro expansion".to_string(),&token.span);
+                    return false;
+                }
+                TokenKind::Hash if definition.params.is_some()
+                    && body.get(index+1).and_then(|next| definition.param_index(next)).is_none() => {
+                    self.report_error("'#' is not followed by a macro parameter".to_string(),&token.span);
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    fn process_undef(&mut self,line:&[Token]) {
+        match line.get(2).and_then(identifier_name) {
+            Some(name) => {
+                self.macros.remove(name);
+            }
+            None => self.report_error("Macro name missing".to_string(),&line[1].span),
+        }
+    }
+
+    fn process_include(&mut self,line:&[Token],output:&mut Vec<Token>) {
+        let mut operands = line[2..].to_vec();
+        if operands.first().is_some_and(|token| token.span.literal != "<" && !matches!(token.kind,TokenKind::StringLiteral(_))) {
+            operands = self.expand(&operands);
+        }
+        let (name,quoted) = match operands.first().map(|token| &token.kind) {
+            // The name is taken as written, escape sequences are not replaced in it.
+            Some(TokenKind::StringLiteral(_)) => (operands[0].span.literal.trim_matches('"').to_string(),true),
+            Some(_) if operands[0].span.literal == "<" => {
+                match operands.iter().position(|token| token.span.literal == ">") {
+                    Some(end) => (spell_tokens(&operands[1..end]),false),
+                    None => {
+                        self.report_error("Missing '>' in #include".to_string(),&line[1].span);
+                        return;
+                    }
+                }
+            }
+            _ => {
+                self.report_error("#include expects \"FILENAME\" or <FILENAME>".to_string(),&line[1].span);
+                return;
+            }
+        };
+        let Some(path) = self.find_include(&name,quoted,line[0].span.file) else {
+            self.report_error(format!("Include file not found: {}",name),&line[1].span);
+            return;
+        };
+        if self.include_depth >= MAX_INCLUDE_DEPTH {
+            self.report_error(format!("#include nested too deeply: {}",name),&line[1].span);
+            return;
+        }
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                self.include_depth += 1;
+                output.extend(self.preprocess_file(path.to_string_lossy().into_owned(),text));
+                self.include_depth -= 1;
+            }
+            Err(err) => self.report_error(format!("Cannot read {}: {}",path.display(),err),&line[1].span),
+        }
+    }
+
+    /// Quoted names are searched next to the including file first, then in the include paths.
+    fn find_include(&self,name:&str,quoted:bool,file:usize) -> Option<PathBuf> {
+        let mut directories = vec![];
+        if quoted {
+            let including = Path::new(self.sources.path(file));
+            directories.push(including.parent().map(Path::to_path_buf).unwrap_or_default());
+        }
+        directories.extend(self.include_paths.iter().cloned());
+        directories.into_iter()
+            .map(|directory| directory.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Expands the macros in `tokens`.
+    fn expand(&mut self,tokens:&[Token]) -> Vec<Token> {
+        let input = tokens.iter().map(|token| (token.clone(),vec![])).collect();
+        self.expand_hidden(input).into_iter().map(|(token,_)| token).collect()
+    }
+
+    /// Expands the macros in `input`, each token paired with the macros it may no longer invoke.
+    /// A replacement list goes back in front of the remaining input to be rescanned with it, so a
+    /// function-like macro named at its end takes its arguments from the tokens that follow.
+    fn expand_hidden(&mut self,mut input:VecDeque<HiddenToken>) -> Vec<HiddenToken> {
+        let mut output = vec![];
+        while let Some((token,hidden)) = input.pop_front() {
+            let definition = match identifier_name(&token) {
+                Some(name) if !hidden.iter().any(|macro_name| macro_name == name) => self.macros.get(name).cloned(),
+                _ => None,
+            };
+            let Some(definition) = definition else {
+                output.push((token,hidden));
+                continue;
+            };
+            let name = token.span.literal.clone();
+            let mut invocation = token.span.clone();
+            let (replacement,mut hidden) = match &definition.params {
+                None => (self.substitute(&definition,&[],&[],&invocation),hidden),
+                Some(params) => {
+                    if input.front().map(|(token,_)| &token.kind) != Some(&TokenKind::LeftParen) {
+                        output.push((token,hidden));
+                        continue;
+                    }
+                    let Some((args,end)) = self.collect_arguments(input.iter(),&token) else {
+                        output.push((token,hidden));
+                        output.extend(input.drain(..));
+                        break;
+                    };
+                    let (right_paren,paren_hidden) = &input[end-1];
+                    if right_paren.span.file == invocation.file {
+                        invocation.end = right_paren.span.end;
+                    }
+                    let hidden: HideSet = hidden.into_iter().filter(|macro_name| paren_hidden.contains(macro_name)).collect();
+                    input.drain(..end);
+                    let args = if params.is_empty() && args.len() == 1 && args[0].is_empty() { vec![] } else { args };
+                    if args.len() != params.len() {
+                        self.report_error(
+                            format!("Macro {} expects {} arguments, but {} given",name,params.len(),args.len()),
+                            &invocation,
+                        );
+                        continue;
+                    }
+                    let expanded_args: Vec<Vec<HiddenToken>> = args.iter()
+                        .map(|arg| self.expand_hidden(arg.iter().cloned().collect()))
+                        .collect();
+                    (self.substitute(&definition,&args,&expanded_args,&invocation),hidden)
+                }
+            };
+            hidden.push(name);
+            for (token,mut token_hidden) in replacement.into_iter().rev() {
+                for macro_name in &hidden {
+                    if !token_hidden.contains(macro_name) {
+                        token_hidden.push(macro_name.clone());
+                    }
+                }
+                input.push_front((token,token_hidden));
+            }
+        }
+        output
+    }
+
+    /// Splits the arguments of a call whose `(` is the first of `tokens`, returning them with the number of tokens up to `)`.
+    fn collect_arguments<'a>(&mut self,tokens:impl Iterator<Item=&'a HiddenToken>,name:&Token) -> Option<(Vec<Vec<HiddenToken>>,usize)> {
+        let mut args = vec![vec![]];
+        let mut depth = 0;
+        for (index,(token,hidden)) in tokens.enumerate().skip(1) {
+            match token.kind {
+                TokenKind::LeftParen => depth += 1,
+                TokenKind::RightParen if depth == 0 => return Some((args,index+1)),
+                TokenKind::RightParen => depth -= 1,
+                TokenKind::Comma if depth == 0 => {
+                    args.push(vec![]);
+                    continue;
+                }
+                _ => {}
+            }
+            args.last_mut().unwrap().push((token.clone(),hidden.clone()));
+        }
+        self.report_error(format!("Unterminated argument list invoking macro {}",name.span.literal),&name.span);
+        None
+    }
+
+    /// Replaces the parameters in the body of the macro, applying `#` and `##`. The tokens of the
+    /// body are placed at `invocation`; argument tokens keep their places and the macros hidden
+    /// from them, and a pasted token keeps those hidden from both its operands.
+    fn substitute(&mut self,definition:&Macro,args:&[Vec<HiddenToken>],expanded_args:&[Vec<HiddenToken>],invocation:&TextSpan) -> Vec<HiddenToken> {
+        let body = &definition.body;
+        let mut result: Vec<HiddenToken> = vec![];
+        let mut paste_next = false;
+        let mut left_empty = false;
+        let mut index = 0;
+        while index < body.len() {
+            let token = &body[index];
+            index += 1;
+            if token.kind == TokenKind::HashHash {
+                paste_next = true;
+                continue;
+            }
+            let stringified = if token.kind == TokenKind::Hash && definition.params.is_some() {
+                definition.param_index(&body[index])
+            } else {
+                None
+            };
+            let piece = if let Some(param) = stringified {
+                index += 1;
+                let arg: Vec<Token> = args[param].iter().map(|(token,_)| token.clone()).collect();
+                vec![(placed_at(&stringify(&arg,token),invocation),vec![])]
+            } else if let Some(param) = definition.param_index(token) {
+                let pasted = paste_next || body.get(index).is_some_and(|next| next.kind == TokenKind::HashHash);
+                if pasted { args[param].clone() } else { expanded_args[param].clone() }
+            } else {
+                vec![(placed_at(token,invocation),vec![])]
+            };
+            match (paste_next && !left_empty,result.pop(),piece.first()) {
+                (true,Some((left,left_hidden)),Some((right,right_hidden))) => {
+                    let hidden: HideSet = left_hidden.into_iter().filter(|macro_name| right_hidden.contains(macro_name)).collect();
+                    let pasted = self.paste(&left,right);
+                    result.extend(pasted.iter().map(|token| (placed_at(token,invocation),hidden.clone())));
+                    result.extend(piece[1..].iter().cloned());
+                }
+                (_,left,_) => {
+                    result.extend(left);
+                    result.extend(piece.iter().cloned());
+                }
+            }
+            left_empty = piece.is_empty();
+            paste_next = false;
+        }
+        result
+    }
+
+    fn paste(&mut self,left:&Token,right:&Token) -> Vec<Token> {
+        let spelling = format!("{}{}",left.span.literal,right.span.literal);
+        let mut lexer = Lexer::new(&spelling);
+        let tokens: Vec<Token> = std::iter::from_fn(|| lexer.next_token())
+            .filter(|token| token.kind != TokenKind::Eof)
+            .collect();
+        match tokens.as_slice() {
+            [token] if !token.kind.is_trivia() && token.kind != TokenKind::Bad => {
+                let span = TextSpan::new(left.span.start,left.span.end,spelling).in_file(left.span.file);
+                vec![Token::new(token.kind.clone(),span)]
+            }
+            _ => {
+                self.report_error(
+                    format!("Pasting \"{}\" and \"{}\" does not give a valid preprocessing token",left.span.literal,right.span.literal),
+                    &left.span,
+                );
+                vec![left.clone(),right.clone()]
+            }
+        }
+    }
+
+    fn report_error(&self,message:String,span:&TextSpan) {
+        self.diagnostics_bag.borrow_mut().report_error(message,span.clone());
+    }
+}
+
+/// The token spelled as `token` at `span`, where a macro invocation put it.
+fn placed_at(token:&Token,span:&TextSpan) -> Token {
+    Token::new(token.kind.clone(),TextSpan::new(span.start,span.end,token.span.literal.clone()).in_file(span.file))
+}
+
+/// Turns the tokens of a macro argument into a string literal, as `#` does.
+fn stringify(arg:&[Token],hash:&Token) -> Token {
+    let spelling = spell_tokens(arg);
+    let mut value = String::new();
+    for (index,token) in arg.iter().enumerate() {
+        if index > 0 && !is_adjacent(&arg[index-1],token) {
+            value.push(' ');
+        }
+        if matches!(token.kind,TokenKind::StringLiteral(_) | TokenKind::CharLiteral(_)) {
+            value.push_str(&token.span.literal.replace('\\',"\\\\").replace('"',"\\\""));
+        } else {
+            value.push_str(&token.span.literal);
+        }
+    }
+    let span = TextSpan::new(hash.span.start,hash.span.end,format!("\"{}\"",value)).in_file(hash.span.file);
+    Token::new(TokenKind::StringLiteral(spelling.into_bytes()),span)
+}
@@ -1,11 +1,11 @@
 use crate::ast::*;
 use crate::ast::lexer::TextSpan;
 use crate::ast::progranunit::ASTFunction;
-use crate::ast::statement::ASTStatement;
 use crate::ast::visitor::ASTVisitor;
 
 pub struct ASTPrinter{
     pub(crate) indent : usize,
+    pub(crate) output : String,
 }
 const LEVEL_INDENT: usize = 2;
 impl ASTVisitor for ASTPrinter {
@@ -27,15 +27,30 @@ impl ASTVisitor for ASTPrinter {
     fn visit_assignment(&mut self, assignment: &ASTAssignment) {
         self.print_with_indent("Assignment:");
         self.indent+=LEVEL_INDENT;
-        self.print_with_indent(&format!("Name: {}",assignment.name));
+        self.visit_left_value(&assignment.name);
+        if let Some(operator) = &assignment.operator {
+            self.print_with_indent(&format!("Operator: {:?}",operator.kind));
+        }
         self.do_visit_expression(&assignment.expr);
         self.indent-=LEVEL_INDENT;
     }
 
+    fn visit_increment(&mut self, increment: &ASTIncrementExpression) {
+        let position = if increment.postfix { "Postfix" } else { "Prefix" };
+        self.print_with_indent(&format!("{} {:?}:",position,increment.kind));
+        self.indent+=LEVEL_INDENT;
+        self.visit_left_value(&increment.name);
+        self.indent-=LEVEL_INDENT;
+    }
+
     fn visit_number(&mut self, number: &ASTNumberExpression) {
         self.print_with_indent(&format!("Number: {}",number.value));
     }
 
+    fn visit_string(&mut self, string: &ASTStringExpression) {
+        self.print_with_indent(&format!("String: \"{}\"",string.value.escape_ascii()));
+    }
+
     fn visit_binary_expression(&mut self, binary: &ASTBinaryExpression) {
         self.print_with_indent("Binary Expression:");
         self.indent+=LEVEL_INDENT;
@@ -44,6 +59,33 @@ impl ASTVisitor for ASTPrinter {
         self.do_visit_expression(&binary.right);
         self.indent-=LEVEL_INDENT;
     }
+    fn visit_unary_expression(&mut self, unary: &ASTUnaryExpression) {
+        self.print_with_indent("Unary Expression:");
+        self.indent+=LEVEL_INDENT;
+        self.print_with_indent(&format!("Operator: {:?}",unary.operator.kind));
+        self.do_visit_expression(&unary.operand);
+        self.indent-=LEVEL_INDENT;
+    }
+    fn visit_cast_expression(&mut self, cast: &ASTCastExpression, ty: &GrammarVartype) {
+        self.print_with_indent(&format!("Cast Expression: {}",ty));
+        self.visit_indented_expression(&cast.operand);
+    }
+    fn visit_conditional_expression(&mut self, conditional: &ASTConditionalExpression) {
+        self.print_with_indent("Conditional Expression:");
+        self.indent+=LEVEL_INDENT;
+        self.print_with_indent("Condition:");
+        self.visit_indented_expression(&conditional.condition);
+        self.print_with_indent("Then:");
+        self.visit_indented_expression(&conditional.then_expr);
+        self.print_with_indent("Else:");
+        self.visit_indented_expression(&conditional.else_expr);
+        self.indent-=LEVEL_INDENT;
+    }
+    fn visit_member_expression(&mut self, member: &ASTMemberExpression) {
+        let operator = if member.arrow { "->" } else { "." };
+        self.print_with_indent(&format!("Member Access: {}{}",operator,member.name));
+        self.visit_indented_expression(&member.base);
+    }
     fn visit_error(&mut self, span: &TextSpan) {
         self.print_with_indent(&format!("Error: {:?}",span));
     }
@@ -61,11 +103,11 @@ impl ASTVisitor for ASTPrinter {
 
     fn visit_declaration(&mut self, declaration: &ASTDeclaration) {
         match declaration { 
-            ASTDeclaration::VariableDeclareDirect(name) => {
-                self.print_with_indent(&format!("Variable: {}",name));
+            ASTDeclaration::VariableDeclareDirect(name,vartype) => {
+                self.print_with_indent(&format!("Variable: {}: {}",name,vartype));
             }
-            ASTDeclaration::VariableDeclareWithInit(name,expr) => {
-                self.print_with_indent(&format!("Variable: {}",name));
+            ASTDeclaration::VariableDeclareWithInit(name,vartype,expr) => {
+                self.print_with_indent(&format!("Variable: {}: {}",name,vartype));
                 self.do_visit_expression(expr);
             }
         }
@@ -96,16 +138,159 @@ impl ASTVisitor for ASTPrinter {
         self.print_with_indent("Empty Return");
     }
 
-    fn visit_function_call(&mut self, function_call: &ASTFuncionCall) {
-        self.print_with_indent(&format!("Function call: {}",function_call.name));
+    fn visit_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        self.print_with_indent("If:");
         self.indent+=LEVEL_INDENT;
+        self.print_with_indent("Condition:");
+        self.visit_indented_expression(&if_statement.condition);
+        self.print_with_indent("Then:");
+        self.visit_indented_statement(&if_statement.then_branch);
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.print_with_indent("Else:");
+            self.visit_indented_statement(else_branch);
+        }
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        self.print_with_indent("While:");
+        self.indent+=LEVEL_INDENT;
+        self.print_with_indent("Condition:");
+        self.visit_indented_expression(&while_statement.condition);
+        self.print_with_indent("Body:");
+        self.visit_indented_statement(&while_statement.body);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_do_while_statement(&mut self, do_while_statement: &ASTDoWhileStatement) {
+        self.print_with_indent("Do While:");
+        self.indent+=LEVEL_INDENT;
+        self.print_with_indent("Body:");
+        self.visit_indented_statement(&do_while_statement.body);
+        self.print_with_indent("Condition:");
+        self.visit_indented_expression(&do_while_statement.condition);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_for_statement(&mut self, for_statement: &ASTForStatement) {
+        self.print_with_indent("For:");
+        self.indent+=LEVEL_INDENT;
+        if let Some(init) = &for_statement.init {
+            self.print_with_indent("Init:");
+            self.indent+=LEVEL_INDENT;
+            self.visit_statement(init);
+            self.indent-=LEVEL_INDENT;
+        }
+        if let Some(condition) = &for_statement.condition {
+            self.print_with_indent("Condition:");
+            self.visit_indented_expression(condition);
+        }
+        if let Some(step) = &for_statement.step {
+            self.print_with_indent("Step:");
+            self.visit_indented_expression(step);
+        }
+        self.print_with_indent("Body:");
+        self.visit_indented_statement(&for_statement.body);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_break(&mut self) {
+        self.print_with_indent("Break");
+    }
+
+    fn visit_continue(&mut self) {
+        self.print_with_indent("Continue");
+    }
+
+    fn visit_goto(&mut self, label: &str) {
+        self.print_with_indent(&format!("Goto: {}",label));
+    }
+
+    fn visit_labeled_statement(&mut self, labeled: &ASTLabeledStatement) {
+        self.print_with_indent(&format!("Label: {}",labeled.label));
+        self.indent+=LEVEL_INDENT;
+        self.visit_statement(&labeled.statement);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_block(&mut self, statements: &[ASTStatement]) {
+        self.print_with_indent("Block:");
+        self.indent+=LEVEL_INDENT;
+        statements.iter().for_each(|x|self.visit_statement(x));
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_switch_statement(&mut self, switch: &ASTSwitchStatement) {
+        self.print_with_indent("Switch:");
+        self.indent+=LEVEL_INDENT;
+        self.print_with_indent("Condition:");
+        self.visit_indented_expression(&switch.condition);
+        self.print_with_indent("Body:");
+        self.visit_indented_statement(&switch.body);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_case_statement(&mut self, case: &ASTCaseStatement) {
+        self.print_with_indent(&format!("Case: {}",case.value));
+        self.indent+=LEVEL_INDENT;
+        self.do_visit_expression(&case.expr);
+        self.visit_statement(&case.statement);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_default_statement(&mut self, statement: &ASTStatement) {
+        self.print_with_indent("Default:");
+        self.indent+=LEVEL_INDENT;
+        self.visit_statement(statement);
+        self.indent-=LEVEL_INDENT;
+    }
+
+    fn visit_function_call(&mut self, function_call: &ASTFuncionCall) {
+        if let ASTExpressionKind::Variable(variable) = &function_call.callee.kind {
+            self.print_with_indent(&format!("Function call: {}",variable.name));
+            self.indent+=LEVEL_INDENT;
+        } else {
+            self.print_with_indent("Function call:");
+            self.indent+=LEVEL_INDENT;
+            self.print_with_indent("Callee:");
+            self.visit_indented_expression(&function_call.callee);
+        }
         function_call.param_list.iter().for_each(|expr|self.do_visit_expression(expr));
         self.indent-=LEVEL_INDENT;
     }
 }
 
+impl Default for ASTPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ASTPrinter{
+    pub fn new() -> Self{
+        Self{indent:0,output:String::new()}
+    }
+    fn visit_indented_expression(&mut self,expr:&ASTExpression){
+        self.indent+=LEVEL_INDENT;
+        self.do_visit_expression(expr);
+        self.indent-=LEVEL_INDENT;
+    }
+    fn visit_left_value(&mut self,left_value:&LeftValue){
+        match left_value {
+            LeftValue::Variable(name) => self.print_with_indent(&format!("Name: {}",name)),
+            LeftValue::Deref(unary) => {
+                self.print_with_indent("Deref:");
+                self.visit_indented_expression(&unary.operand);
+            }
+            LeftValue::Member(member) => self.visit_member_expression(member),
+        }
+    }
+    fn visit_indented_statement(&mut self,statement:&ASTStatement){
+        self.indent+=LEVEL_INDENT;
+        self.visit_statement(statement);
+        self.indent-=LEVEL_INDENT;
+    }
     fn print_with_indent(&mut self,text:&str){
-        println!("{}{}"," ".repeat(self.indent),text)
+        self.output.push_str(&format!("{}{}\n"," ".repeat(self.indent),text));
     }
 }
\ No newline at end of file
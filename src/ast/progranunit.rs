@@ -1,4 +1,3 @@
-use crate::ast::expression::ASTBinaryExpression;
 use crate::ast::{GrammarFunctiontype, GrammarVartype};
 use crate::ast::statement::*;
 
@@ -38,7 +37,10 @@ impl ASTFunction{
             params,
         }
     }
-    
+    pub fn function_type(&self) -> &GrammarFunctiontype{
+        &self.function_type
+    }
+
 }
 
 #[derive(Debug,Clone)]
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+/// Lexically nested scopes mapping names to `T`, the outermost one being the file scope.
+/// Lookups go from the innermost scope outwards, so inner declarations shadow outer ones.
+#[derive(Debug,Clone)]
+pub struct ScopeStack<T>{
+    scopes:Vec<HashMap<String,T>>,
+}
+
+impl<T> Default for ScopeStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ScopeStack<T>{
+    pub fn new() -> Self{
+        Self{scopes:vec![HashMap::new()]}
+    }
+    pub fn push(&mut self){
+        self.scopes.push(HashMap::new());
+    }
+    pub fn pop(&mut self){
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+    pub fn is_file_scope(&self) -> bool{
+        self.scopes.len() == 1
+    }
+    /// Declares `name` in the innermost scope, returning the value it replaces there.
+    pub fn declare(&mut self,name:String,value:T) -> Option<T>{
+        self.scopes.last_mut().unwrap().insert(name,value)
+    }
+    pub fn is_declared_in_current_scope(&self,name:&str) -> bool{
+        self.scopes.last().unwrap().contains_key(name)
+    }
+    pub fn lookup(&self,name:&str) -> Option<&T>{
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+    pub fn lookup_mut(&mut self,name:&str) -> Option<&mut T>{
+        self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name))
+    }
+}
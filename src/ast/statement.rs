@@ -6,6 +6,27 @@ pub enum ASTStatementKind{
     Declaration(ASTDeclarationList),
     Return(ASTExpression),
     EmptyReturn,
+    If(ASTIfStatement),
+    While(ASTWhileStatement),
+    DoWhile(ASTDoWhileStatement),
+    For(ASTForStatement),
+    Break,
+    Continue,
+    Goto(String),
+    Labeled(ASTLabeledStatement),
+    Switch(ASTSwitchStatement),
+    Case(ASTCaseStatement),
+    Default(Box<ASTStatement>),
+    Block(Vec<ASTStatement>),
+    Empty,
+}
+
+/// Where a `goto` or a `switch` jumps to.
+#[derive(Debug,Clone,PartialEq)]
+pub enum ASTJumpTarget{
+    Label(String),
+    Case(i64),
+    Default,
 }
 #[derive(Debug,Clone)]
 pub struct ASTStatement{
@@ -28,13 +49,131 @@ impl ASTStatement {
     pub fn empty_return() -> Self {
         ASTStatement::new(ASTStatementKind::EmptyReturn)
     }
+    pub fn if_statement(condition: ASTExpression, then_branch: ASTStatement, else_branch: Option<ASTStatement>) -> Self {
+        ASTStatement::new(ASTStatementKind::If(ASTIfStatement { condition, then_branch: Box::new(then_branch), else_branch: else_branch.map(Box::new) }))
+    }
+    pub fn while_statement(condition: ASTExpression, body: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::While(ASTWhileStatement { condition, body: Box::new(body) }))
+    }
+    pub fn do_while_statement(body: ASTStatement, condition: ASTExpression) -> Self {
+        ASTStatement::new(ASTStatementKind::DoWhile(ASTDoWhileStatement { body: Box::new(body), condition }))
+    }
+    pub fn for_statement(init: Option<ASTStatement>, condition: Option<ASTExpression>, step: Option<ASTExpression>, body: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::For(ASTForStatement { init: init.map(Box::new), condition, step, body: Box::new(body) }))
+    }
+    pub fn break_statement() -> Self {
+        ASTStatement::new(ASTStatementKind::Break)
+    }
+    pub fn continue_statement() -> Self {
+        ASTStatement::new(ASTStatementKind::Continue)
+    }
+    pub fn goto_statement(label: String) -> Self {
+        ASTStatement::new(ASTStatementKind::Goto(label))
+    }
+    pub fn labeled(label: String, statement: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::Labeled(ASTLabeledStatement { label, statement: Box::new(statement) }))
+    }
+    pub fn empty() -> Self {
+        ASTStatement::new(ASTStatementKind::Empty)
+    }
+    pub fn switch_statement(condition: ASTExpression, body: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::Switch(ASTSwitchStatement { condition, body: Box::new(body) }))
+    }
+    pub fn block(statements: Vec<ASTStatement>) -> Self {
+        ASTStatement::new(ASTStatementKind::Block(statements))
+    }
+    pub fn case_statement(expr: ASTExpression, value: i64, statement: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::Case(ASTCaseStatement { expr, value, statement: Box::new(statement) }))
+    }
+    pub fn default_statement(statement: ASTStatement) -> Self {
+        ASTStatement::new(ASTStatementKind::Default(Box::new(statement)))
+    }
+    /// Tells whether `target` is defined by this statement or one nested in it. The `case`
+    /// and `default` labels of a nested `switch` belong to that switch and are not searched.
+    pub fn contains_target(&self, target: &ASTJumpTarget) -> bool {
+        match &self.kind {
+            ASTStatementKind::Labeled(labeled) => {
+                *target == ASTJumpTarget::Label(labeled.label.clone()) || labeled.statement.contains_target(target)
+            }
+            ASTStatementKind::Case(case) => {
+                *target == ASTJumpTarget::Case(case.value) || case.statement.contains_target(target)
+            }
+            ASTStatementKind::Default(statement) => {
+                *target == ASTJumpTarget::Default || statement.contains_target(target)
+            }
+            ASTStatementKind::If(if_statement) => {
+                if_statement.then_branch.contains_target(target)
+                    || if_statement.else_branch.as_ref().is_some_and(|branch| branch.contains_target(target))
+            }
+            ASTStatementKind::While(while_statement) => while_statement.body.contains_target(target),
+            ASTStatementKind::DoWhile(do_while_statement) => do_while_statement.body.contains_target(target),
+            ASTStatementKind::For(for_statement) => for_statement.body.contains_target(target),
+            ASTStatementKind::Switch(switch) => {
+                matches!(target, ASTJumpTarget::Label(_)) && switch.body.contains_target(target)
+            }
+            ASTStatementKind::Block(statements) => Self::list_contains_target(statements, target),
+            _ => false,
+        }
+    }
+    pub fn list_contains_target(statements: &[ASTStatement], target: &ASTJumpTarget) -> bool {
+        statements.iter().any(|statement| statement.contains_target(target))
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTSwitchStatement{
+    pub(crate) condition:ASTExpression,
+    pub(crate) body:Box<ASTStatement>,
+}
+
+/// `case expr: statement`, with `value` the folded integer constant expression.
+#[derive(Debug,Clone)]
+pub struct ASTCaseStatement{
+    pub(crate) expr:ASTExpression,
+    pub(crate) value:i64,
+    pub(crate) statement:Box<ASTStatement>,
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTLabeledStatement{
+    pub(crate) label:String,
+    pub(crate) statement:Box<ASTStatement>,
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTIfStatement{
+    pub(crate) condition:ASTExpression,
+    pub(crate) then_branch:Box<ASTStatement>,
+    pub(crate) else_branch:Option<Box<ASTStatement>>,
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTWhileStatement{
+    pub(crate) condition:ASTExpression,
+    pub(crate) body:Box<ASTStatement>,
+}
+
+#[derive(Debug,Clone)]
+pub struct ASTDoWhileStatement{
+    pub(crate) body:Box<ASTStatement>,
+    pub(crate) condition:ASTExpression,
+}
+
+/// `for (init; condition; step) body`, where `init` is a declaration or an expression statement.
+#[derive(Debug,Clone)]
+pub struct ASTForStatement{
+    pub(crate) init:Option<Box<ASTStatement>>,
+    pub(crate) condition:Option<ASTExpression>,
+    pub(crate) step:Option<ASTExpression>,
+    pub(crate) body:Box<ASTStatement>,
 }
 
 
 #[derive(Debug,Clone)]
+/// A declarator of a declaration list, with the type it gives the name.
 pub enum ASTDeclaration{
-    VariableDeclareDirect(String),
-    VariableDeclareWithInit(String,ASTExpression),
+    VariableDeclareDirect(String,GrammarVartype),
+    VariableDeclareWithInit(String,GrammarVartype,ASTExpression),
 }
 
 #[derive(Debug,Clone)]
@@ -0,0 +1,83 @@
+use std::fmt::Display;
+use crate::ast::GrammarVartype;
+use crate::ast::lexer::VartypeKind;
+
+/// A value of the interpreted program: integers and addresses, or floating values.
+///
+/// A `float` is kept as the `f64` it converts to exactly, so that only conversions and
+/// the results of arithmetic on `float` operands need rounding to single precision.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Value{
+    Integer(i64),
+    Floating(f64),
+}
+
+impl Value{
+    /// The integer value; a floating value is truncated toward zero.
+    pub fn as_integer(self) -> i64{
+        match self {
+            Value::Integer(value) => value,
+            Value::Floating(value) => value as i64,
+        }
+    }
+    pub fn as_floating(self) -> f64{
+        match self {
+            Value::Integer(value) => value as f64,
+            Value::Floating(value) => value,
+        }
+    }
+    /// Whether the value compares unequal to 0, as conditions test it.
+    pub fn is_true(self) -> bool{
+        match self {
+            Value::Integer(value) => value != 0,
+            Value::Floating(value) => value != 0.0,
+        }
+    }
+    /// Converts a value of type `from` to type `to`, as a cast or an assignment does.
+    /// Floating values convert to integers by truncation, and integers to the nearest
+    /// floating value.
+    pub fn convert(self,from:&GrammarVartype,to:&GrammarVartype) -> Value{
+        let to_float = *to == GrammarVartype::Direct(VartypeKind::Float);
+        if to.is_floating() {
+            return Value::Floating(match self {
+                Value::Floating(value) if to_float => value as f32 as f64,
+                Value::Floating(value) => value,
+                Value::Integer(value) if from.is_unsigned() && to_float => value as u64 as f32 as f64,
+                Value::Integer(value) if from.is_unsigned() => value as u64 as f64,
+                Value::Integer(value) if to_float => value as f32 as f64,
+                Value::Integer(value) => value as f64,
+            });
+        }
+        let value = match self {
+            Value::Integer(value) => value,
+            Value::Floating(value) if to.is_unsigned() => value as u64 as i64,
+            Value::Floating(value) => value as i64,
+        };
+        Value::Integer(to.convert(value))
+    }
+    /// The bits an object of type `ty` holding this value is made of.
+    pub fn to_bits(self,ty:&GrammarVartype) -> i64{
+        match ty {
+            GrammarVartype::Direct(VartypeKind::Float) => (self.as_floating() as f32).to_bits() as i64,
+            GrammarVartype::Direct(VartypeKind::Double) => self.as_floating().to_bits() as i64,
+            _ => self.as_integer(),
+        }
+    }
+    /// The value of an object of type `ty` made of `bits`.
+    pub fn from_bits(bits:i64,ty:&GrammarVartype) -> Value{
+        match ty {
+            GrammarVartype::Direct(VartypeKind::Float) => Value::Floating(f32::from_bits(bits as u32) as f64),
+            GrammarVartype::Direct(VartypeKind::Double) => Value::Floating(f64::from_bits(bits as u64)),
+            _ => Value::Integer(ty.convert(bits)),
+        }
+    }
+}
+
+impl Display for Value{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f,"{}",value),
+            Value::Floating(value) => write!(f,"{:?}",value),
+        }
+    }
+}
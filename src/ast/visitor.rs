@@ -1,7 +1,6 @@
 use crate::ast::*;
 use crate::ast::lexer::TextSpan;
 use crate::ast::progranunit::*;
-use crate::ast::statement::*;
 
 pub trait ASTVisitor {
     
@@ -29,6 +28,43 @@ pub trait ASTVisitor {
             ASTStatementKind::EmptyReturn=>{
                 self.visit_empty_return();
             }
+            ASTStatementKind::If(if_statement)=>{
+                self.visit_if_statement(if_statement);
+            }
+            ASTStatementKind::While(while_statement)=>{
+                self.visit_while_statement(while_statement);
+            }
+            ASTStatementKind::DoWhile(do_while_statement)=>{
+                self.visit_do_while_statement(do_while_statement);
+            }
+            ASTStatementKind::For(for_statement)=>{
+                self.visit_for_statement(for_statement);
+            }
+            ASTStatementKind::Break=>{
+                self.visit_break();
+            }
+            ASTStatementKind::Continue=>{
+                self.visit_continue();
+            }
+            ASTStatementKind::Goto(label)=>{
+                self.visit_goto(label);
+            }
+            ASTStatementKind::Labeled(labeled)=>{
+                self.visit_labeled_statement(labeled);
+            }
+            ASTStatementKind::Switch(switch)=>{
+                self.visit_switch_statement(switch);
+            }
+            ASTStatementKind::Case(case)=>{
+                self.visit_case_statement(case);
+            }
+            ASTStatementKind::Default(statement)=>{
+                self.visit_default_statement(statement);
+            }
+            ASTStatementKind::Block(statements)=>{
+                self.visit_block(statements);
+            }
+            ASTStatementKind::Empty=>{}
         }
     }
     fn do_visit_expression(&mut self, expr:&ASTExpression){
@@ -36,15 +72,33 @@ pub trait ASTVisitor {
             ASTExpressionKind::Number(number) =>{
                 self.visit_number(number);
             }
+            ASTExpressionKind::String(string) =>{
+                self.visit_string(string);
+            }
             ASTExpressionKind::Binary(binary) => {
                 self.visit_binary_expression(binary);
             }
+            ASTExpressionKind::Unary(unary) => {
+                self.visit_unary_expression(unary);
+            }
+            ASTExpressionKind::Cast(cast) => {
+                self.visit_cast_expression(cast,&expr.ty);
+            }
+            ASTExpressionKind::Conditional(conditional) => {
+                self.visit_conditional_expression(conditional);
+            }
+            ASTExpressionKind::Member(member) => {
+                self.visit_member_expression(member);
+            }
             ASTExpressionKind::Variable(variable)=>{
                 self.visit_variable(variable);
             }
             ASTExpressionKind::Assignment(assignment) =>{
                 self.visit_assignment(assignment);
             }
+            ASTExpressionKind::Increment(increment) =>{
+                self.visit_increment(increment);
+            }
             ASTExpressionKind::Error(span) =>{
                 self.visit_error(span);
             }
@@ -56,8 +110,14 @@ pub trait ASTVisitor {
     fn visit_statement(&mut self,statement:&ASTStatement);
     fn visit_expression(&mut self,expr:&ASTExpression);
     fn visit_assignment(&mut self,assignment:&ASTAssignment);
+    fn visit_increment(&mut self,increment:&ASTIncrementExpression);
     fn visit_number(&mut self,number:&ASTNumberExpression);
+    fn visit_string(&mut self,string:&ASTStringExpression);
     fn visit_binary_expression(&mut self,binary:&ASTBinaryExpression);
+    fn visit_unary_expression(&mut self,unary:&ASTUnaryExpression);
+    fn visit_cast_expression(&mut self,cast:&ASTCastExpression,ty:&GrammarVartype);
+    fn visit_conditional_expression(&mut self,conditional:&ASTConditionalExpression);
+    fn visit_member_expression(&mut self,member:&ASTMemberExpression);
     fn visit_error(&mut self,span:&TextSpan);
     fn visit_variable(&mut self,variable:&ASTVariableExpression);
     fn visit_declaration_list(&mut self,declaration_list:&ASTDeclarationList);
@@ -66,5 +126,17 @@ pub trait ASTVisitor {
     fn visit_function(&mut self,function:&ASTFunction);
     fn visit_return(&mut self,expr:&ASTExpression);
     fn visit_empty_return(&mut self);
+    fn visit_if_statement(&mut self,if_statement:&ASTIfStatement);
+    fn visit_while_statement(&mut self,while_statement:&ASTWhileStatement);
+    fn visit_do_while_statement(&mut self,do_while_statement:&ASTDoWhileStatement);
+    fn visit_for_statement(&mut self,for_statement:&ASTForStatement);
+    fn visit_break(&mut self);
+    fn visit_continue(&mut self);
+    fn visit_goto(&mut self,label:&str);
+    fn visit_labeled_statement(&mut self,labeled:&ASTLabeledStatement);
+    fn visit_block(&mut self,statements:&[ASTStatement]);
+    fn visit_switch_statement(&mut self,switch:&ASTSwitchStatement);
+    fn visit_case_statement(&mut self,case:&ASTCaseStatement);
+    fn visit_default_statement(&mut self,statement:&ASTStatement);
     fn visit_function_call(&mut self,function_call:&ASTFuncionCall);
 }
\ No newline at end of file
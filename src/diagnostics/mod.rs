@@ -30,6 +30,12 @@ pub struct DiagnosticBag{
     pub diagnostics: Vec<Diagnostic>,
 }
 
+impl Default for DiagnosticBag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DiagnosticBag{
     pub fn new() -> Self {
         Self { diagnostics: Vec::new() }
@@ -37,6 +43,9 @@ impl DiagnosticBag{
     pub fn report_error(&mut self,message:String,span:TextSpan) {
         self.diagnostics.push(Diagnostic::new(message, span, DiagnosticKind::Error));
     }
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| matches!(diagnostic.kind, DiagnosticKind::Error))
+    }
     pub fn report_warning(&mut self,message:String,span:TextSpan) {
         self.diagnostics.push(Diagnostic::new(message, span, DiagnosticKind::Warning));
     }
@@ -53,5 +62,17 @@ impl DiagnosticBag{
             token.span.clone()
         )
     }
+    pub fn report_expected_identifier(&mut self, token: &Token) {
+        self.report_error(
+            format!("Expected identifier, found: {:?}", token.kind),
+            token.span.clone()
+        )
+    }
+    pub fn report_expected_type(&mut self, token: &Token) {
+        self.report_error(
+            format!("Expected type, found: {:?}", token.kind),
+            token.span.clone()
+        )
+    }
 }
 
@@ -1,42 +1,51 @@
 use std::cmp;
-use crate::diagnostics::Diagnostic;
-use crate::text::SourceText;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::text::SourceMap;
 
 pub struct DiagnosticsPrinter<'a>{
-    text: &'a SourceText,
+    sources: &'a SourceMap,
     diagnostic: &'a [Diagnostic],
 }
 
 const PREFIX_LENGTH:usize = 8;
 
 impl <'a> DiagnosticsPrinter<'a>{
-    pub fn new(text:&'a SourceText,diagnostic:&'a [Diagnostic]) -> Self {
-        Self { text, diagnostic }
+    pub fn new(sources:&'a SourceMap,diagnostic:&'a [Diagnostic]) -> Self {
+        Self { sources, diagnostic }
     }
 
     pub fn stringify_diagnostic(&self, diagnostic: &Diagnostic) -> String {
-        let line_index = self.text.line_index(diagnostic.span.start);
-        let line = self.text.get_line(line_index);
-        let line_start = self.text.line_start(line_index);
-        let column = diagnostic.span.start-line_start ;
-        let prefix_start = cmp::max(0,column as isize - PREFIX_LENGTH as isize) as usize;
+        let text = self.sources.source(diagnostic.span.file);
+        let line_index = text.line_index(diagnostic.span.start);
+        let line = text.get_line(line_index);
+        let line_start = text.line_start(line_index);
+        // Offsets are in bytes, moved back to the start of the character they fall in.
+        let boundary = |index:usize| (0..=index).rev().find(|&index| line.is_char_boundary(index)).unwrap_or(0);
+        let column = boundary(cmp::min(diagnostic.span.start-line_start,line.len()));
+        let prefix_start = boundary(column.saturating_sub(PREFIX_LENGTH));
         let prefix_end = column;
-        let suffix_start = cmp::min(column+diagnostic.span.length(),line.len())+ 1;
-        let suffix_end = cmp::min(suffix_start + PREFIX_LENGTH , line.len());
+        let suffix_start = boundary(cmp::min(column+diagnostic.span.length(),line.len()));
+        let suffix_end = boundary(cmp::min(suffix_start + PREFIX_LENGTH , line.len()));
         let prefix = &line[prefix_start..prefix_end];
         let span = &line[prefix_end..suffix_start];
         let suffix = &line[suffix_start..suffix_end];
-        let indent = cmp::min(PREFIX_LENGTH,column);
-        let arrow_pointers = format!("{:indent$}^{}","","^".repeat(diagnostic.span.length() -1),indent = indent);
+        let length = cmp::max(span.chars().count(),1);
+        let indent = prefix.chars().count();
+        let arrow_pointers = format!("{:indent$}^{}","","^".repeat(length -1),indent = indent);
         let arrow_line = format!("{:indent$}|","",indent = indent);
         let error_message = format!("{:indent$}+--{}","",diagnostic.message,indent = indent);
+        let kind = match diagnostic.kind {
+            DiagnosticKind::Error => "error",
+            DiagnosticKind::Warning => "warning",
+        };
+        let location = format!("{}:{}:{}: {}",self.sources.path(diagnostic.span.file),line_index+1,column+1,kind);
         format!(
-            "{prefix}{span}{suffix}\n{arrow_pointers}\n{arrow_line}\n{error_message}"
+            "{location}\n{prefix}{span}{suffix}\n{arrow_pointers}\n{arrow_line}\n{error_message}"
         )
     }
     pub fn print(&self) {
         for diagnostic in self.diagnostic {
-            println!("{}", self.stringify_diagnostic(diagnostic));
+            eprintln!("{}", self.stringify_diagnostic(diagnostic));
         }
     }
 }
\ No newline at end of file
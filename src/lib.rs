@@ -0,0 +1,120 @@
+//! Front end of the C compiler as a library.
+//!
+//! The pipeline stages can be run one by one with [`lex`], [`preprocess`],
+//! [`parse`] and [`evaluate`], or all at once with [`compile`].
+
+pub mod ast;
+pub mod diagnostics;
+pub mod text;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::ast::Ast;
+use crate::ast::evaluator::ASTEvaluator;
+use crate::ast::lexer::{Lexer, Token};
+use crate::ast::parser::Parser;
+use crate::ast::preprocessor::{Preprocessor, PreprocessorOptions};
+use crate::diagnostics::{Diagnostic, DiagnosticBag, DiagnosticKind};
+use crate::diagnostics::printer::DiagnosticsPrinter;
+use crate::text::SourceMap;
+
+/// Everything the front end produced for one source file.
+pub struct CompilationResult {
+    /// The main file and every file it included.
+    pub sources: SourceMap,
+    /// The preprocessed tokens handed to the parser.
+    pub tokens: Vec<Token>,
+    pub ast: Ast,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Result of interpreting the program, `None` when it was not run because of errors or
+    /// stopped on a run-time error, which is then the last diagnostic.
+    pub evaluation: Option<i64>,
+}
+
+impl CompilationResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|diagnostic| matches!(diagnostic.kind, DiagnosticKind::Error))
+    }
+    /// Renders every diagnostic against the source text, as the driver prints them.
+    pub fn diagnostics_report(&self) -> String {
+        render_diagnostics(&self.sources,&self.diagnostics)
+    }
+}
+
+/// Splits the source into tokens, whitespace included.
+pub fn lex(source:&str) -> Vec<Token> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token(){
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Runs the preprocessor on the file at `path` whose contents are `source`.
+pub fn preprocess(path:&str,source:&str,options:&PreprocessorOptions) -> (Vec<Token>,SourceMap,Vec<Diagnostic>) {
+    let diagnostics_bag = Rc::new(RefCell::new(DiagnosticBag::new()));
+    let mut preprocessor = Preprocessor::new(options,diagnostics_bag.clone());
+    let tokens = preprocessor.preprocess(path,source.to_string());
+    let diagnostics = std::mem::take(&mut diagnostics_bag.borrow_mut().diagnostics);
+    (tokens,preprocessor.into_sources(),diagnostics)
+}
+
+/// Parses the tokens into an [`Ast`], returning it with the diagnostics reported on the way.
+pub fn parse(tokens:Vec<Token>) -> (Ast,Vec<Diagnostic>) {
+    let diagnostics_bag = Rc::new(RefCell::new(DiagnosticBag::new()));
+    let mut ast = Ast::new();
+    let mut parser = Parser::new(tokens,diagnostics_bag.clone());
+    while let Some(prog) = parser.next_program_unit(){
+        ast.add_program_unit(prog);
+    }
+    let diagnostics = std::mem::take(&mut diagnostics_bag.borrow_mut().diagnostics);
+    (ast,diagnostics)
+}
+
+/// Interprets the program starting from `main`, returning the value `main` returns, or the
+/// run-time error that stopped it.
+pub fn evaluate(ast:&Ast) -> Result<Option<i64>,Diagnostic> {
+    let mut eval = ASTEvaluator::new();
+    ast.visit(&mut eval);
+    eval.run_main();
+    match eval.fault {
+        Some(fault) => Err(fault),
+        None => Ok(eval.result),
+    }
+}
+
+/// Runs the whole pipeline, evaluating the program only when no error was reported.
+///
+/// Quoted includes are searched from the current directory.
+pub fn compile(source:&str) -> CompilationResult {
+    compile_file("<input>",source,&PreprocessorOptions::default())
+}
+
+/// Like [`compile`] for the file at `path`, with the given preprocessor options.
+pub fn compile_file(path:&str,source:&str,options:&PreprocessorOptions) -> CompilationResult {
+    let (tokens,sources,mut diagnostics) = preprocess(path,source,options);
+    let (ast,parse_diagnostics) = parse(tokens.clone());
+    diagnostics.extend(parse_diagnostics);
+    let mut result = CompilationResult{
+        sources,
+        tokens,
+        ast,
+        diagnostics,
+        evaluation: None,
+    };
+    if !result.has_errors() {
+        match evaluate(&result.ast) {
+            Ok(evaluation) => result.evaluation = evaluation,
+            Err(fault) => result.diagnostics.push(fault),
+        }
+    }
+    result
+}
+
+pub fn render_diagnostics(sources:&SourceMap,diagnostics:&[Diagnostic]) -> String {
+    let printer = DiagnosticsPrinter::new(sources,diagnostics);
+    diagnostics.iter()
+        .map(|diagnostic| format!("{}\n",printer.stringify_diagnostic(diagnostic)))
+        .collect()
+}
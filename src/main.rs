@@ -1,49 +1,206 @@
-mod ast;
-mod diagnostics;
-mod text;
-
-use std::cell::RefCell;
-use std::rc::Rc;
-use std::fs::File;
-use std::io::{self,Read};
-use crate::ast::Ast;
-use crate::ast::parser::Parser;
-use crate::ast::evaluator::ASTEvaluator;
-
-fn main() {
-    let mut file = File::open("test.c").unwrap();
-    let mut input = String::new();
-    file.read_to_string(&mut input);
-    let file_str:&str = &input.clone();
-    let text = text::SourceText::new(input);
-    let mut lexer = ast::lexer::Lexer::new(file_str);
-    let mut tokens = Vec::new();
-    while let Some(token) = lexer.next_token(){
-        tokens.push(token);
-    }
-    println!("{:?}",tokens);
-    let diagnostics_bag = Rc::new(RefCell::new(diagnostics::DiagnosticBag::new()));
-
-    let mut ast: Ast = Ast::new();
-    let mut parser = Parser::new(tokens,diagnostics_bag.clone());
-    while let Some(prog) = parser.next_program_unit(){
-        ast.add_program_unit(prog);
-    }
-    let diagnositcs_binding = diagnostics_bag.borrow();
-    if diagnositcs_binding.diagnostics.len()>0{
-        let diagnostics_printer = diagnostics::printer::DiagnosticsPrinter::new(
-          &text,
-          &diagnositcs_binding.diagnostics
-        );
-        diagnostics_printer.print();
-        return;
-    }
-    else {
-        ast.visualize();
-
-    }
-    let mut eval = ASTEvaluator::new();
-    ast.visit(&mut eval);
-    println!("{:?}",eval.last_value);
+use std::fs;
+use std::process::ExitCode;
+use std::path::PathBuf;
+use compiler::ast::lexer::Token;
+use compiler::ast::preprocessor::{print_preprocessed, MacroOption, PreprocessorOptions};
+use compiler::diagnostics::DiagnosticKind;
 
+const USAGE: &str = "usage: compiler [options] <file>...
+options:
+  -o <file>          write the output to <file>
+  --emit=<stage>     stop after <stage> and print it: tokens, ast, ir, asm
+  --run              interpret the program, exiting with the result of main
+  -E                 only run the preprocessor and print its output
+  -D <name>[=<value>] define a macro, as `1` when no value is given
+  -U <name>          undefine a macro
+  -I <dir>           add a directory to the include search path
+  -h, --help         print this message";
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum EmitKind {
+    Preprocessed,
+    Tokens,
+    Ast,
+    Ir,
+    Asm,
+}
+
+impl EmitKind {
+    fn from_name(name:&str) -> Option<Self> {
+        match name {
+            "tokens" => Some(EmitKind::Tokens),
+            "ast" => Some(EmitKind::Ast),
+            "ir" => Some(EmitKind::Ir),
+            "asm" => Some(EmitKind::Asm),
+            _ => None,
+        }
+    }
+}
+
+struct Options {
+    inputs: Vec<String>,
+    output: Option<String>,
+    emit: Option<EmitKind>,
+    run: bool,
+    preprocessor: PreprocessorOptions,
+}
+
+impl Options {
+    fn parse(args:impl Iterator<Item=String>) -> Result<Option<Self>,String> {
+        let mut options = Options{
+            inputs: Vec::new(),
+            output: None,
+            emit: None,
+            run: false,
+            preprocessor: PreprocessorOptions::default(),
+        };
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--run" => options.run = true,
+                "-E" => options.emit = Some(EmitKind::Preprocessed),
+                "-o" => {
+                    let output = args.next().ok_or("missing filename after '-o'")?;
+                    options.output = Some(output);
+                }
+                _ if arg.starts_with("--emit=") => {
+                    let stage = &arg["--emit=".len()..];
+                    let emit = EmitKind::from_name(stage)
+                        .ok_or(format!("unknown stage '{}' for '--emit'",stage))?;
+                    options.emit = Some(emit);
+                }
+                "-D" | "-U" | "-I" => {
+                    let value = args.next().ok_or(format!("missing argument after '{}'",arg))?;
+                    options.add_preprocessor_option(&arg,&value);
+                }
+                _ if arg.starts_with("-o") => options.output = Some(arg[2..].to_string()),
+                _ if arg.starts_with("-D") || arg.starts_with("-U") || arg.starts_with("-I") => {
+                    options.add_preprocessor_option(&arg[..2],&arg[2..]);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unrecognized option '{}'",arg));
+                }
+                _ => options.inputs.push(arg),
+            }
+        }
+        if options.inputs.is_empty() {
+            return Err("no input files".to_string());
+        }
+        if options.emit.is_some() && options.run {
+            return Err("'--emit' or '-E' cannot be used together with '--run'".to_string());
+        }
+        if options.output.is_some() && options.inputs.len() > 1 {
+            return Err("cannot specify '-o' with multiple input files".to_string());
+        }
+        if options.output.is_some() && options.emit.is_none() {
+            return Err("'-o' requires '-E' or an '--emit' stage to write".to_string());
+        }
+        Ok(Some(options))
+    }
+    fn add_preprocessor_option(&mut self,flag:&str,value:&str) {
+        let preprocessor = &mut self.preprocessor;
+        match flag {
+            "-D" => preprocessor.macro_options.push(MacroOption::define(value)),
+            "-U" => preprocessor.macro_options.push(MacroOption::Undefine(value.to_string())),
+            _ => preprocessor.include_paths.push(PathBuf::from(value)),
+        }
+    }
+}
+
+fn format_tokens(tokens:&[Token]) -> String {
+    tokens.iter()
+        .filter(|token| !token.kind.is_trivia())
+        .map(|token| format!("{:?}\n",token))
+        .collect()
+}
+
+fn write_output(options:&Options,output:&str) -> Result<(),String> {
+    match &options.output {
+        Some(path) => fs::write(path,output)
+            .map_err(|err| format!("cannot write '{}': {}",path,err)),
+        None => {
+            print!("{}",output);
+            Ok(())
+        }
+    }
+}
+
+/// Runs the pipeline on one input file and returns the exit code it asks for.
+fn compile_file(options:&Options,path:&str) -> Result<u8,String> {
+    let input = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read '{}': {}",path,err))?;
+    let (tokens,sources,diagnostics) = compiler::preprocess(path,&input,&options.preprocessor);
+    let has_errors = |diagnostics:&[compiler::diagnostics::Diagnostic]| {
+        diagnostics.iter().any(|diagnostic| matches!(diagnostic.kind,DiagnosticKind::Error))
+    };
+    if !diagnostics.is_empty(){
+        eprint!("{}",compiler::render_diagnostics(&sources,&diagnostics));
+        if has_errors(&diagnostics){
+            return Ok(1);
+        }
+    }
+    if options.emit == Some(EmitKind::Preprocessed) {
+        write_output(options,&print_preprocessed(&tokens,&sources))?;
+        return Ok(0);
+    }
+    if options.emit == Some(EmitKind::Tokens) {
+        write_output(options,&format_tokens(&tokens))?;
+        return Ok(0);
+    }
+    let (ast,diagnostics) = compiler::parse(tokens);
+    if !diagnostics.is_empty(){
+        eprint!("{}",compiler::render_diagnostics(&sources,&diagnostics));
+        if has_errors(&diagnostics){
+            return Ok(1);
+        }
+    }
+    match options.emit {
+        Some(EmitKind::Ast) => {
+            write_output(options,&ast.visualize())?;
+            return Ok(0);
+        }
+        Some(EmitKind::Ir) => return Err("'--emit=ir' is not supported yet: there is no IR generator".to_string()),
+        Some(EmitKind::Asm) => return Err("'--emit=asm' is not supported yet: there is no code generator".to_string()),
+        _ => {}
+    }
+    if options.run {
+        return match compiler::evaluate(&ast) {
+            Ok(result) => Ok(result.unwrap_or(0) as u8),
+            Err(fault) => {
+                eprint!("{}",compiler::render_diagnostics(&sources,&[fault]));
+                Ok(1)
+            }
+        };
+    }
+    Ok(0)
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}",USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n{}",message,USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut exit_code = 0;
+    for path in &options.inputs {
+        match compile_file(&options,path) {
+            Ok(code) => {
+                if code != 0 {
+                    exit_code = code;
+                }
+            }
+            Err(message) => {
+                eprintln!("error: {}",message);
+                exit_code = 1;
+            }
+        }
+    }
+    ExitCode::from(exit_code)
 }
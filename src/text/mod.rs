@@ -1,17 +1,57 @@
 pub struct SourceText{
     text:String,
+    line_starts:Vec<usize>,
 }
 impl SourceText{
     pub fn new(text:String) -> Self {
-        Self { text }
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index,_)| index+1))
+            .collect();
+        Self { text, line_starts }
+    }
+    pub fn text(&self) -> &str {
+        &self.text
     }
     pub fn line_index(&self,position:usize) -> usize {
-        self.text[..position].lines().count() -1
+        self.line_starts.partition_point(|&start| start <= position) - 1
     }
     pub fn get_line(&self,position:usize) -> &str {
-        self.text.lines().nth(position).unwrap()
+        self.text.lines().nth(position).unwrap_or("")
     }
     pub fn line_start(&self,index:usize) -> usize{
-        self.text.lines().take(index).map(|line| line.len()+1).sum()
+        self.line_starts[index]
+    }
+}
+
+/// Every file read during a compilation, indexed by the `file` of a `TextSpan`.
+pub struct SourceMap{
+    files:Vec<(String,SourceText)>,
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceMap{
+    pub fn new() -> Self {
+        Self { files: Vec::new() }
     }
-}
\ No newline at end of file
+    pub fn add_file(&mut self,path:String,text:String) -> usize {
+        self.files.push((path,SourceText::new(text)));
+        self.files.len()-1
+    }
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    pub fn path(&self,file:usize) -> &str {
+        &self.files[file].0
+    }
+    pub fn source(&self,file:usize) -> &SourceText {
+        &self.files[file].1
+    }
+}
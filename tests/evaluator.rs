@@ -0,0 +1,36 @@
+/// The message of the only error `source` reports, which must compile but fail when run.
+fn run_time_error(source:&str) -> String {
+    let result = compiler::compile(source);
+    assert_eq!(result.evaluation,None);
+    assert_eq!(result.diagnostics.len(),1,"{}",result.diagnostics_report());
+    result.diagnostics[0].message.clone()
+}
+
+#[test]
+fn division_by_zero_stops_the_program() {
+    assert_eq!(run_time_error("int main(){ int x = 0; return 7 % x; }"),"Division by zero");
+}
+
+#[test]
+fn null_pointer_dereference_stops_the_program() {
+    let source = "struct s { int a; int b; };\nint main(){ struct s *p = 0; p->b = 4; return 3; }";
+    assert_eq!(run_time_error(source),"Dereference of a null pointer");
+}
+
+#[test]
+fn call_through_a_null_function_pointer_stops_the_program() {
+    let message = run_time_error("int main(){ int (*g)(int) = 0; return g(1); }");
+    assert!(message.starts_with("Call through a pointer to no function"),"{}",message);
+}
+
+#[test]
+fn run_time_error_in_a_callee_stops_its_callers() {
+    let source = "int f(int n){ return 10 / n; }\nint g(int *p){ *p = 1; return 0; }\nint main(){ int i; int t = 0; for (i = 3; i >= 0; i--) t = t + f(i); return g(0); }";
+    assert_eq!(run_time_error(source),"Division by zero");
+}
+
+#[test]
+fn writing_to_a_string_literal_stops_the_program() {
+    let message = run_time_error("int main(){ char *s = \"abc\"; s[0] = 'x'; return s[0]; }");
+    assert!(message.starts_with("Invalid memory access"),"{}",message);
+}
@@ -0,0 +1,126 @@
+use compiler::ast::lexer::TokenKind;
+
+/// The kinds of the tokens of `source`, without trivia and the end of file.
+fn kinds(source:&str) -> Vec<TokenKind> {
+    compiler::lex(source).into_iter()
+        .map(|token| token.kind)
+        .filter(|kind| !kind.is_trivia() && *kind != TokenKind::Eof)
+        .collect()
+}
+
+/// What `main` returns when `source` compiles without diagnostics.
+fn run(source:&str) -> i64 {
+    let result = compiler::compile(source);
+    assert!(result.diagnostics.is_empty(),"{}",result.diagnostics_report());
+    result.evaluation.expect("main was not run")
+}
+
+#[test]
+fn floating_constants_are_lexed() {
+    assert_eq!(kinds("2.75 1e-3 .5 2. 0x1.8p1 1.5f 2.5L 7"),[
+        TokenKind::FloatNumber(2.75),
+        TokenKind::FloatNumber(1e-3),
+        TokenKind::FloatNumber(0.5),
+        TokenKind::FloatNumber(2.0),
+        TokenKind::FloatNumber(3.0),
+        TokenKind::FloatNumber(1.5),
+        TokenKind::FloatNumber(2.5),
+        TokenKind::Number(7),
+    ]);
+}
+
+#[test]
+fn floating_arithmetic_and_conversions() {
+    let source = r#"
+int main(){
+    double d = 1.0 / 3.0;
+    float f = 0.1f;
+    int truncated = 7.9;
+    int negative = -7.9;
+    double sum = 0.0;
+    for (int i = 0; i < 10; i++) sum = sum + 0.1;
+    return (d * 3.0 == 1.0) + (f != 0.1) * 10 + truncated * 100 + (negative == -7) * 1000 + (sum < 1.0) * 10000;
+}
+"#;
+    assert_eq!(run(source),1 + 10 + 700 + 1000 + 10000);
+}
+
+/// The messages of the diagnostics `source` reports.
+fn messages(source:&str) -> Vec<String> {
+    compiler::compile(source).diagnostics.into_iter().map(|diagnostic| diagnostic.message).collect()
+}
+
+#[test]
+fn escape_sequences_are_decoded() {
+    assert_eq!(kinds(r"'a' '\n' '\x41' '\101' '\0' '\\' '\''"),[
+        TokenKind::CharLiteral(97),
+        TokenKind::CharLiteral(10),
+        TokenKind::CharLiteral(65),
+        TokenKind::CharLiteral(65),
+        TokenKind::CharLiteral(0),
+        TokenKind::CharLiteral(92),
+        TokenKind::CharLiteral(39),
+    ]);
+    assert_eq!(kinds(r#""tab\thereé""#),[TokenKind::StringLiteral(b"tab\there\xc3\xa9".to_vec())]);
+}
+
+#[test]
+fn string_literals_are_static_char_arrays() {
+    let source = r#"
+int length(char *s){ int n = 0; while (s[n]) n++; return n; }
+int main(){
+    char *s = "ab" "c\x64";
+    char copy[] = "xyz";
+    return length(s) + sizeof "ab" "cd" * 10 + (s[3] == 'd') * 100 + sizeof copy * 1000;
+}
+"#;
+    assert_eq!(run(source),4 + 50 + 100 + 4000);
+}
+
+#[test]
+fn malformed_literals_are_errors() {
+    assert_eq!(messages("int main(){ char *s = \"abc\n; return 0; }"),["Missing terminating \" character"]);
+    assert_eq!(messages("int main(){ return 'a\n; }"),["Missing terminating ' character"]);
+    assert_eq!(messages(r"int main(){ return '\q'; }"),[r"Unknown escape sequence: \q"]);
+    assert_eq!(messages(r"int main(){ return ''; }"),["Empty character constant"]);
+    assert_eq!(messages(r"int main(){ return '\x100'; }"),["Hex escape sequence out of range"]);
+}
+
+#[test]
+fn comments_are_kept_as_trivia() {
+    let source = "a /* one\ntwo */ b // three\nc";
+    let tokens = compiler::lex(source);
+    let comments:Vec<&str> = tokens.iter()
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| token.span.literal.as_str())
+        .collect();
+    assert_eq!(comments,["/* one\ntwo */","// three"]);
+    let c = tokens.iter().find(|token| token.span.literal == "c").unwrap();
+    assert_eq!(c.span.start,source.len() - 1);
+    assert_eq!(kinds("x/**/y // z"),kinds("x y"));
+}
+
+#[test]
+fn commented_out_code_does_not_run() {
+    let source = r#"
+int main(){
+    int x = 1; // x = 2;
+    /* x = 3;
+       x = 4; */
+    x = x /* + 10 */ * 5; /* trailing */
+    return x;
+}
+"#;
+    assert_eq!(run(source),5);
+}
+
+#[test]
+fn unterminated_block_comments_are_errors() {
+    let source = "int main(){ return 0; }\n/* open";
+    let result = compiler::compile(source);
+    assert_eq!(result.diagnostics.len(),1);
+    assert_eq!(result.diagnostics[0].message,"Unterminated /* comment");
+    assert_eq!(result.diagnostics[0].span.start,source.find("/*").unwrap());
+    let result = compiler::compile("#if 0\n/* open\n#endif\nint main(){ return 0; }\n");
+    assert_eq!(result.diagnostics[0].message,"Unterminated /* comment");
+}
@@ -0,0 +1,173 @@
+use compiler::ast::lexer::{Token, TokenKind};
+use compiler::ast::preprocessor::{MacroOption, PreprocessorOptions};
+
+/// The spellings of the tokens, one space apart.
+fn spell(tokens:&[Token]) -> String {
+    tokens.iter()
+        .filter(|token| !token.kind.is_trivia() && token.kind != TokenKind::Eof)
+        .map(|token| token.span.literal.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn assert_preprocesses_to(source:&str,expected:&str) {
+    let (tokens,_,diagnostics) = compiler::preprocess("<input>",source,&PreprocessorOptions::default());
+    assert!(diagnostics.is_empty(),"unexpected diagnostics for {:?}",source);
+    assert_eq!(spell(&tokens),spell(&compiler::lex(expected)));
+}
+
+/// What `main` returns when `source` compiles without diagnostics.
+fn run_with(source:&str,options:&PreprocessorOptions) -> i64 {
+    let result = compiler::compile_file("<input>",source,options);
+    assert!(result.diagnostics.is_empty(),"{}",result.diagnostics_report());
+    result.evaluation.expect("main was not run")
+}
+
+fn run(source:&str) -> i64 {
+    run_with(source,&PreprocessorOptions::default())
+}
+
+#[test]
+fn macro_replacement_examples_of_the_standard() {
+    // C11 6.10.3.5, example 3.
+    let source = r#"
+#define x 3
+#define f(a) f(x * (a))
+#undef x
+#define x 2
+#define g f
+#define z z[0]
+#define h g(~
+#define m(a) a(w)
+#define w 0,1
+#define t(a) a
+#define p() int
+#define q(x) x
+#define r(x,y) x ## y
+#define str(x) # x
+f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);
+g(x+(3,4)-w) | h 5) & m
+(f)^m(m);
+p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };
+char c[2][6] = { str(hello), str() };
+"#;
+    let expected = r#"
+f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);
+f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);
+int i[] = { 1, 23, 4, 5, };
+char c[2][6] = { "hello", "" };
+"#;
+    assert_preprocesses_to(source,expected);
+}
+
+#[test]
+fn macro_pasting_examples_of_the_standard() {
+    // C11 6.10.3.5, example 4, without the #include line.
+    let source = r#"
+#define str(s) # s
+#define xstr(s) str(s)
+#define INCFILE(n) vers ## n
+#define glue(a, b) a ## b
+#define xglue(a, b) glue(a, b)
+#define HIGHLOW "hello"
+#define LOW LOW ", world"
+xstr(INCFILE(2).h)
+glue(HIGH, LOW);
+xglue(HIGH, LOW)
+"#;
+    let expected = r#"
+"vers2.h"
+"hello";
+"hello" ", world"
+"#;
+    assert_preprocesses_to(source,expected);
+}
+
+#[test]
+fn self_referential_macro_in_an_argument_is_not_expanded_again() {
+    assert_preprocesses_to("#define z z[0]\n#define g(x) x\ng(z)\n","z[0]");
+}
+
+#[test]
+fn function_like_macro_takes_arguments_after_a_replacement() {
+    assert_preprocesses_to("#define f(x) (x*2)\n#define g f\ng(3)\n","(3*2)");
+}
+
+#[test]
+fn macros_expand_in_compiled_programs() {
+    let source = r#"
+#define SQUARE(x) ((x) * (x))
+#define MAX(a, b) ((a) > (b) ? (a) : (b))
+#define FIELD(n) field_ ## n
+#define NAME(x) #x
+int main(){
+    int FIELD(1) = 3;
+    int field_2 = SQUARE(FIELD(1) + 1);
+    return MAX(field_1, field_2) + sizeof NAME(abc);
+}
+"#;
+    assert_eq!(run(source),20);
+}
+
+#[test]
+fn command_line_definitions_apply_before_the_source() {
+    let options = PreprocessorOptions{
+        include_paths: Vec::new(),
+        macro_options: vec![MacroOption::define("N=5"),MacroOption::define("ON"),MacroOption::define("OFF"),MacroOption::Undefine("OFF".to_string())],
+    };
+    let source = "#ifdef OFF\nint main(){ return 0; }\n#else\nint main(){ return N + ON; }\n#endif\n";
+    assert_eq!(run_with(source,&options),6);
+}
+
+#[test]
+fn macro_called_with_the_wrong_number_of_arguments_is_an_error() {
+    let result = compiler::compile("#define ADD(a, b) a + b\nint main(){ return ADD(1); }\n");
+    assert!(result.has_errors());
+    assert_eq!(result.diagnostics[0].message,"Macro ADD expects 2 arguments, but 1 given");
+}
+
+#[test]
+fn conditional_groups_select_the_code_compiled() {
+    let source = r#"
+#define VERSION 3
+#if VERSION >= 3 && defined(VERSION)
+int a = 1;
+#elif VERSION == 2
+int a = 2;
+#else
+int a = 3;
+#endif
+#ifdef MISSING
+int b = 10;
+#else
+int b = 20;
+#endif
+#ifndef GUARD
+#define GUARD
+int c = 100;
+#endif
+#ifndef GUARD
+int c = 1000;
+#endif
+#if 0
+#if 1
+this is never parsed
+#endif
+#elif (1 << 4) % 5 == 1 && !defined MISSING
+int d = 1000;
+#endif
+int main(){ return a + b + c + d - 1000; }
+"#;
+    assert_eq!(run(source),121);
+}
+
+#[test]
+fn unbalanced_conditionals_are_errors() {
+    let result = compiler::compile("#if 1\nint main(){ return 0; }\n");
+    assert!(result.has_errors());
+    assert_eq!(result.diagnostics[0].message,"Unterminated conditional directive");
+    let result = compiler::compile("int main(){ return 0; }\n#endif\n");
+    assert_eq!(result.diagnostics[0].message,"#endif without #if");
+    let result = compiler::compile("#if 1\n#else\n#elif 1\n#endif\nint main(){ return 0; }\n");
+    assert_eq!(result.diagnostics[0].message,"#elif after #else");
+}
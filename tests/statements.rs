@@ -0,0 +1,176 @@
+/// What `main` returns when `source` compiles without diagnostics.
+fn run(source:&str) -> i64 {
+    let result = compiler::compile(source);
+    assert!(result.diagnostics.is_empty(),"{}",result.diagnostics_report());
+    result.evaluation.expect("main was not run")
+}
+
+/// The messages of the diagnostics `source` reports.
+fn messages(source:&str) -> Vec<String> {
+    compiler::compile(source).diagnostics.into_iter().map(|diagnostic| diagnostic.message).collect()
+}
+
+#[test]
+fn break_and_continue_leave_the_innermost_loop() {
+    let source = r#"
+int main(){
+    int total = 0;
+    int i = 0;
+    while (i < 10) {
+        i++;
+        if (i % 2 == 0) continue;
+        int j = 0;
+        while (1) {
+            if (j == i) break;
+            j++;
+        }
+        total = total + j;
+        if (i == 7) break;
+    }
+    return total;
+}
+"#;
+    assert_eq!(run(source),1 + 3 + 5 + 7);
+}
+
+#[test]
+fn goto_jumps_forward_and_backward() {
+    let source = r#"
+int main(){
+    int n = 0;
+again:
+    n = n + 3;
+    if (n < 10) goto again;
+    goto done;
+    n = 100;
+done:
+    return n;
+}
+"#;
+    assert_eq!(run(source),12);
+}
+
+#[test]
+fn goto_into_a_loop_body_runs_the_rest_of_the_loop() {
+    let source = r#"
+int main(){
+    int i = 5;
+    int count = 0;
+    goto inside;
+    while (i < 8) {
+        i = i + 10;
+    inside:
+        count++;
+        i++;
+    }
+    return count * 100 + i;
+}
+"#;
+    assert_eq!(run(source),217);
+}
+
+#[test]
+fn misplaced_jumps_are_errors() {
+    assert_eq!(messages("int main(){ break; return 0; }"),["'break' statement not in loop"]);
+    assert_eq!(messages("int main(){ continue; return 0; }"),["'continue' statement not in loop"]);
+    assert_eq!(messages("int main(){ goto nowhere; return 0; }"),["Use of undeclared label: nowhere"]);
+    assert_eq!(messages("int main(){ l: ; l: return 0; }"),["Redefinition of label: l"]);
+}
+
+#[test]
+fn switch_falls_through_until_break() {
+    let source = r#"
+int classify(int n){
+    int result = 0;
+    switch (n) {
+        case 1:
+            result = result + 1;
+        case 2:
+        case 3:
+            result = result + 10;
+            break;
+        case 2 * 2:
+            result = 100;
+            break;
+        default:
+            result = -1;
+    }
+    return result;
+}
+int main(){
+    return classify(1) * 1000 + classify(3) * 100 + classify(4) + classify(9);
+}
+"#;
+    assert_eq!(run(source),11000 + 1000 + 100 - 1);
+}
+
+#[test]
+fn switch_without_a_matching_case_skips_its_body() {
+    assert_eq!(run("int main(){ int n = 7; switch (n) { case 1: n = 0; } return n; }"),7);
+}
+
+#[test]
+fn invalid_case_labels_are_errors() {
+    assert_eq!(messages("int main(){ switch (1) { case 1: case 1: break; } return 0; }"),["Duplicate case value: 1"]);
+    assert_eq!(messages("int main(){ int x = 1; switch (1) { case x: break; } return 0; }"),["Case label is not an integer constant expression"]);
+    assert_eq!(messages("int main(){ case 1: return 0; }"),["'case' statement not in switch statement"]);
+}
+
+#[test]
+fn inner_blocks_shadow_and_release_their_variables() {
+    let source = r#"
+int x = 1;
+int main(){
+    int result = x;
+    int x = 10;
+    {
+        int x = 100;
+        result = result + x;
+        {
+            x = x + 1;
+            int x = 1000;
+            result = result + x;
+        }
+        result = result + x;
+    }
+    for (int x = 0; x < 3; x++) {
+        result = result + 10000;
+    }
+    return result + x;
+}
+"#;
+    assert_eq!(run(source),1 + 100 + 1000 + 101 + 30000 + 10);
+}
+
+#[test]
+fn block_variables_are_not_visible_after_the_block() {
+    assert_eq!(messages("int main(){ { int y = 1; } return y; }"),["Use of undeclared identifier: y"]);
+    assert_eq!(messages("int main(){ int y = 1; { int y = 2; } int y = 3; return y; }"),["Redefinition of y"]);
+}
+
+#[test]
+fn recursive_calls_keep_their_own_locals() {
+    let source = r#"
+int fib(int n){
+    if (n < 2) return n;
+    int a = fib(n - 1);
+    int b = fib(n - 2);
+    return a + b;
+}
+int main(){ return fib(12); }
+"#;
+    assert_eq!(run(source),144);
+}
+
+#[test]
+fn mutually_recursive_functions_see_each_other() {
+    let source = r#"
+int is_odd(int n);
+int is_even(int n){ return n == 0 ? 1 : is_odd(n - 1); }
+int is_odd(int n){ return n == 0 ? 0 : is_even(n - 1); }
+int calls = 0;
+int depth(int n){ calls++; return n == 0 ? 0 : 1 + depth(n - 1); }
+int main(){ return is_even(10) * 100 + is_odd(7) * 10 + depth(5) + calls; }
+"#;
+    assert_eq!(run(source),100 + 10 + 5 + 6);
+}
@@ -0,0 +1,65 @@
+/// What `main` returns when `source` compiles without diagnostics.
+fn run(source:&str) -> i64 {
+    let result = compiler::compile(source);
+    assert!(result.diagnostics.is_empty(),"{}",result.diagnostics_report());
+    result.evaluation.expect("main was not run")
+}
+
+#[test]
+fn integer_types_have_their_widths() {
+    let source = r#"
+int main(){
+    return sizeof(char) + sizeof(short) * 10 + sizeof(int) * 100 + sizeof(long) * 1000
+        + sizeof(unsigned long long) * 10000 + sizeof(signed char) * 100000;
+}
+"#;
+    assert_eq!(run(source),1 + 20 + 400 + 8000 + 80000 + 100000);
+}
+
+#[test]
+fn unsigned_arithmetic_wraps_around() {
+    let source = r#"
+int main(){
+    unsigned char c = 250;
+    c = c + 10;
+    unsigned int u = 0;
+    u = u - 1;
+    unsigned short s = 65535;
+    s++;
+    unsigned long long big = 18446744073709551615ULL;
+    big = big + 2;
+    return c + (u == 4294967295u) * 100 + s * 1000 + big * 10000;
+}
+"#;
+    assert_eq!(run(source),4 + 100 + 10000);
+}
+
+#[test]
+fn signed_narrow_types_truncate_on_conversion() {
+    let source = r#"
+int main(){
+    signed char c = 200;
+    short s = 70000;
+    return (c == -56) + (s == 4464) * 10;
+}
+"#;
+    assert_eq!(run(source),11);
+}
+
+#[test]
+fn operands_are_promoted_before_arithmetic() {
+    let source = r#"
+int main(){
+    unsigned char a = 200;
+    unsigned char b = 100;
+    int sum = a + b;
+    unsigned int u = 1;
+    int negative = -1;
+    int compare = negative < u;
+    char shifted = 1;
+    return (sum == 300) + compare * 10 + (sizeof(shifted << 1) == sizeof(int)) * 100;
+}
+"#;
+    // -1 converts to unsigned int for the comparison, so it is not less than 1.
+    assert_eq!(run(source),101);
+}